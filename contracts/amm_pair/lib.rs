@@ -0,0 +1,473 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod amm_pair {
+    use assets_extension::*;
+    use sp_arithmetic::helpers_128bit::{multiply_by_rational_with_rounding, sqrt};
+    use sp_arithmetic::Rounding;
+
+    /// Swap fee in basis points, left in the pool for liquidity providers
+    pub const FEE_BPS: u128 = 30;
+    /// LP shares locked in the pool by the first deposit, so that the supply
+    /// never goes back to zero
+    pub const MINIMUM_LIQUIDITY: Balance = 1_000;
+    /// Fixed point scale of the prices summed in the TWAP accumulators
+    pub const PRICE_SCALE: u128 = 1_000_000_000_000_000_000;
+
+    #[ink(event)]
+    pub struct LiquidityAdded {
+        #[ink(topic)]
+        provider: AccountId,
+        amount_a: Balance,
+        amount_b: Balance,
+        liquidity: Balance,
+    }
+
+    #[ink(event)]
+    pub struct LiquidityRemoved {
+        #[ink(topic)]
+        provider: AccountId,
+        amount_a: Balance,
+        amount_b: Balance,
+        liquidity: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Swap {
+        #[ink(topic)]
+        trader: AccountId,
+        #[ink(topic)]
+        asset_in: u128,
+        amount_in: Balance,
+        amount_out: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum AmmError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        /// The pair and LP asset ids must all differ
+        IdenticalAssets,
+        /// The asset is not one of the pair
+        UnknownAsset,
+        ZeroAmount,
+        /// The pool has no reserves yet
+        InsufficientReserves,
+        /// The deposit is too small to mint any LP share
+        InsufficientLiquidityMinted,
+        /// The caller holds fewer LP shares than requested
+        InsufficientLiquidity,
+        /// An amount is below the minimum set by the caller
+        Slippage,
+        Overflow,
+    }
+
+    impl From<AssetsError> for AmmError {
+        fn from(error: AssetsError) -> Self {
+            AmmError::Assets(error)
+        }
+    }
+
+    /// Constant product pool of two pallet assets.
+    ///
+    /// Reserves are the balances the pool holds in the pallet, read back after
+    /// every operation. Liquidity providers own shares of the pool as the LP
+    /// asset, created by the pool which stays its admin.
+    #[ink(storage)]
+    pub struct AmmPair {
+        asset_a: u128,
+        asset_b: u128,
+        lp_asset_id: u128,
+        reserve_a: Balance,
+        reserve_b: Balance,
+        /// Block at which the reserves were last updated
+        block_last: BlockNumber,
+        /// Sum over blocks of the price of `asset_a` in `asset_b`, scaled by `PRICE_SCALE`
+        price_a_cumulative: u128,
+        /// Sum over blocks of the price of `asset_b` in `asset_a`, scaled by `PRICE_SCALE`
+        price_b_cumulative: u128,
+    }
+
+    impl AmmPair {
+        /// Creates `lp_asset_id` with the pool as owner and admin.
+        /// The transferred value should cover the asset deposit
+        #[ink(constructor, payable)]
+        pub fn new(asset_a: u128, asset_b: u128, lp_asset_id: u128) -> Result<Self, AmmError> {
+            if asset_a == asset_b || lp_asset_id == asset_a || lp_asset_id == asset_b {
+                return Err(AmmError::IdenticalAssets);
+            }
+            AssetsExtension::create(Origin::Address, lp_asset_id, Self::env().account_id(), 1)?;
+            Ok(Self {
+                asset_a,
+                asset_b,
+                lp_asset_id,
+                reserve_a: 0,
+                reserve_b: 0,
+                block_last: Self::env().block_number(),
+                price_a_cumulative: 0,
+                price_b_cumulative: 0,
+            })
+        }
+
+        #[ink(message)]
+        pub fn assets(&self) -> (u128, u128) {
+            (self.asset_a, self.asset_b)
+        }
+
+        #[ink(message)]
+        pub fn lp_asset_id(&self) -> u128 {
+            self.lp_asset_id
+        }
+
+        /// Reserves of `asset_a` and `asset_b`, and the block they were last updated at
+        #[ink(message)]
+        pub fn reserves(&self) -> (Balance, Balance, BlockNumber) {
+            (self.reserve_a, self.reserve_b, self.block_last)
+        }
+
+        /// Price accumulators of `asset_a` and `asset_b` up to the current block.
+        /// The TWAP between two observations is the difference of the accumulators
+        /// divided by the number of blocks between them, they wrap on overflow.
+        #[ink(message)]
+        pub fn price_cumulatives(&self) -> (u128, u128, BlockNumber) {
+            let now = self.env().block_number();
+            let (price_a, price_b) = self.accumulated_prices(now);
+            (price_a, price_b, now)
+        }
+
+        /// Amount of the other asset `swap` gives for `amount_in` of `asset_in`
+        #[ink(message)]
+        pub fn quote(&self, asset_in: u128, amount_in: Balance) -> Result<Balance, AmmError> {
+            let (reserve_in, reserve_out) = self.oriented_reserves(asset_in)?;
+            amount_out(amount_in, reserve_in, reserve_out)
+        }
+
+        /// Deposits both assets at the current pool ratio, at most the desired
+        /// amounts and at least the minimums, and mints LP shares to the caller.
+        /// The first deposit sets the ratio.
+        /// Caller should approve contract address as spender of both assets beforehand
+        #[ink(message)]
+        pub fn add_liquidity(
+            &mut self,
+            amount_a_desired: Balance,
+            amount_b_desired: Balance,
+            amount_a_min: Balance,
+            amount_b_min: Balance,
+        ) -> Result<Balance, AmmError> {
+            if amount_a_desired == 0 || amount_b_desired == 0 {
+                return Err(AmmError::ZeroAmount);
+            }
+            let (amount_a, amount_b) = self.optimal_amounts(amount_a_desired, amount_b_desired)?;
+            if amount_a < amount_a_min || amount_b < amount_b_min {
+                return Err(AmmError::Slippage);
+            }
+
+            let supply = AssetsExtension::total_supply(self.lp_asset_id);
+            let liquidity = if supply == 0 {
+                // rounded down geometric mean, without overflowing the product
+                let liquidity = amount_a
+                    .checked_mul(amount_b)
+                    .map(sqrt)
+                    .unwrap_or_else(|| sqrt(amount_a) * sqrt(amount_b));
+                liquidity
+                    .checked_sub(MINIMUM_LIQUIDITY)
+                    .ok_or(AmmError::InsufficientLiquidityMinted)?
+            } else {
+                let liquidity_a = mul_div(amount_a, supply, self.reserve_a)?;
+                let liquidity_b = mul_div(amount_b, supply, self.reserve_b)?;
+                liquidity_a.min(liquidity_b)
+            };
+            if liquidity == 0 {
+                return Err(AmmError::InsufficientLiquidityMinted);
+            }
+
+            let provider = self.env().caller();
+            let contract = self.env().account_id();
+            AssetsExtension::transfer_approved(
+                Origin::Address,
+                self.asset_a,
+                provider,
+                contract,
+                amount_a,
+            )?;
+            AssetsExtension::transfer_approved(
+                Origin::Address,
+                self.asset_b,
+                provider,
+                contract,
+                amount_b,
+            )?;
+            if supply == 0 {
+                AssetsExtension::mint(
+                    Origin::Address,
+                    self.lp_asset_id,
+                    contract,
+                    MINIMUM_LIQUIDITY,
+                )?;
+            }
+            AssetsExtension::mint(Origin::Address, self.lp_asset_id, provider, liquidity)?;
+            self.update();
+            self.env().emit_event(LiquidityAdded {
+                provider,
+                amount_a,
+                amount_b,
+                liquidity,
+            });
+            Ok(liquidity)
+        }
+
+        /// Burns `liquidity` LP shares of the caller and sends the caller its
+        /// share of both reserves, at least the minimums
+        #[ink(message)]
+        pub fn remove_liquidity(
+            &mut self,
+            liquidity: Balance,
+            amount_a_min: Balance,
+            amount_b_min: Balance,
+        ) -> Result<(Balance, Balance), AmmError> {
+            if liquidity == 0 {
+                return Err(AmmError::ZeroAmount);
+            }
+            let provider = self.env().caller();
+            if AssetsExtension::balance_of(self.lp_asset_id, provider) < liquidity {
+                return Err(AmmError::InsufficientLiquidity);
+            }
+            let supply = AssetsExtension::total_supply(self.lp_asset_id);
+            let amount_a = mul_div(liquidity, self.reserve_a, supply)?;
+            let amount_b = mul_div(liquidity, self.reserve_b, supply)?;
+            if amount_a < amount_a_min || amount_b < amount_b_min {
+                return Err(AmmError::Slippage);
+            }
+
+            AssetsExtension::burn(Origin::Address, self.lp_asset_id, provider, liquidity)?;
+            AssetsExtension::transfer(Origin::Address, self.asset_a, provider, amount_a)?;
+            AssetsExtension::transfer(Origin::Address, self.asset_b, provider, amount_b)?;
+            self.update();
+            self.env().emit_event(LiquidityRemoved {
+                provider,
+                amount_a,
+                amount_b,
+                liquidity,
+            });
+            Ok((amount_a, amount_b))
+        }
+
+        /// Swaps `amount_in` of `asset_in` for at least `amount_out_min` of the
+        /// other asset of the pair.
+        /// Caller should approve contract address as spender of `asset_in` beforehand
+        #[ink(message)]
+        pub fn swap(
+            &mut self,
+            asset_in: u128,
+            amount_in: Balance,
+            amount_out_min: Balance,
+        ) -> Result<Balance, AmmError> {
+            let (reserve_in, reserve_out) = self.oriented_reserves(asset_in)?;
+            let amount_out = amount_out(amount_in, reserve_in, reserve_out)?;
+            if amount_out < amount_out_min {
+                return Err(AmmError::Slippage);
+            }
+            let asset_out = if asset_in == self.asset_a {
+                self.asset_b
+            } else {
+                self.asset_a
+            };
+
+            let trader = self.env().caller();
+            AssetsExtension::transfer_approved(
+                Origin::Address,
+                asset_in,
+                trader,
+                self.env().account_id(),
+                amount_in,
+            )?;
+            AssetsExtension::transfer(Origin::Address, asset_out, trader, amount_out)?;
+            self.update();
+            self.env().emit_event(Swap {
+                trader,
+                asset_in,
+                amount_in,
+                amount_out,
+            });
+            Ok(amount_out)
+        }
+
+        /// Deposit matching the pool ratio within the desired amounts
+        fn optimal_amounts(
+            &self,
+            amount_a_desired: Balance,
+            amount_b_desired: Balance,
+        ) -> Result<(Balance, Balance), AmmError> {
+            if self.reserve_a == 0 || self.reserve_b == 0 {
+                return Ok((amount_a_desired, amount_b_desired));
+            }
+            let amount_b = mul_div(amount_a_desired, self.reserve_b, self.reserve_a)?;
+            if amount_b <= amount_b_desired {
+                return Ok((amount_a_desired, amount_b));
+            }
+            let amount_a = mul_div(amount_b_desired, self.reserve_a, self.reserve_b)?;
+            Ok((amount_a, amount_b_desired))
+        }
+
+        /// Reserves of `asset_in` and of the other asset
+        fn oriented_reserves(&self, asset_in: u128) -> Result<(Balance, Balance), AmmError> {
+            if asset_in == self.asset_a {
+                Ok((self.reserve_a, self.reserve_b))
+            } else if asset_in == self.asset_b {
+                Ok((self.reserve_b, self.reserve_a))
+            } else {
+                Err(AmmError::UnknownAsset)
+            }
+        }
+
+        /// Price accumulators with the current reserves counted up to block `now`
+        fn accumulated_prices(&self, now: BlockNumber) -> (u128, u128) {
+            let elapsed = now.saturating_sub(self.block_last) as u128;
+            if elapsed == 0 || self.reserve_a == 0 || self.reserve_b == 0 {
+                return (self.price_a_cumulative, self.price_b_cumulative);
+            }
+            let price = |numerator, denominator| {
+                multiply_by_rational_with_rounding(
+                    numerator,
+                    PRICE_SCALE,
+                    denominator,
+                    Rounding::Down,
+                )
+                .unwrap_or(u128::MAX)
+                .wrapping_mul(elapsed)
+            };
+            (
+                self.price_a_cumulative
+                    .wrapping_add(price(self.reserve_b, self.reserve_a)),
+                self.price_b_cumulative
+                    .wrapping_add(price(self.reserve_a, self.reserve_b)),
+            )
+        }
+
+        /// Accumulates the prices up to this block and reads back the reserves
+        fn update(&mut self) {
+            let now = self.env().block_number();
+            (self.price_a_cumulative, self.price_b_cumulative) = self.accumulated_prices(now);
+            let contract = self.env().account_id();
+            self.reserve_a = AssetsExtension::balance_of(self.asset_a, contract);
+            self.reserve_b = AssetsExtension::balance_of(self.asset_b, contract);
+            self.block_last = now;
+        }
+    }
+
+    /// Output of a swap of `amount_in` against the reserves, after the fee
+    fn amount_out(
+        amount_in: Balance,
+        reserve_in: Balance,
+        reserve_out: Balance,
+    ) -> Result<Balance, AmmError> {
+        if amount_in == 0 {
+            return Err(AmmError::ZeroAmount);
+        }
+        if reserve_in == 0 || reserve_out == 0 {
+            return Err(AmmError::InsufficientReserves);
+        }
+        let amount_in = mul_div(amount_in, 10_000 - FEE_BPS, 10_000)?;
+        let reserve_in = reserve_in
+            .checked_add(amount_in)
+            .ok_or(AmmError::Overflow)?;
+        mul_div(amount_in, reserve_out, reserve_in)
+    }
+
+    /// `a * b / c` rounded down
+    fn mul_div(a: Balance, b: Balance, c: Balance) -> Result<Balance, AmmError> {
+        multiply_by_rational_with_rounding(a, b, c, Rounding::Down).ok_or(AmmError::Overflow)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use assets_extension::mock::AssetsMock;
+        use ink::env::{test, DefaultEnvironment};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const A: u128 = 1;
+        const B: u128 = 2;
+        const LP: u128 = 3;
+
+        fn setup() -> (
+            AmmPair,
+            AssetsMock,
+            test::DefaultAccounts<DefaultEnvironment>,
+        ) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mock = AssetsMock::register(AccountId::from(CONTRACT));
+            for asset_id in [A, B] {
+                mock.create(asset_id, accounts.alice, 1);
+                for account in [accounts.alice, accounts.bob] {
+                    mock.mint(asset_id, account, 1_000_000);
+                    mock.approve(asset_id, account, AccountId::from(CONTRACT), 1_000_000);
+                }
+            }
+            mock.set_caller(accounts.alice);
+            let pair = AmmPair::new(A, B, LP).unwrap();
+            (pair, mock, accounts)
+        }
+
+        #[ink::test]
+        fn adds_and_removes_liquidity() {
+            let (mut pair, mock, accounts) = setup();
+            assert_eq!(mock.asset(LP).unwrap().admin, AccountId::from(CONTRACT));
+
+            assert_eq!(pair.add_liquidity(10_000, 40_000, 0, 0), Ok(19_000));
+            assert_eq!(mock.balance_of(LP, AccountId::from(CONTRACT)), 1_000);
+            assert_eq!(pair.reserves().0, 10_000);
+
+            // deposits follow the pool ratio
+            mock.set_caller(accounts.bob);
+            assert_eq!(
+                pair.add_liquidity(5_000, 5_000, 2_000, 0),
+                Err(AmmError::Slippage)
+            );
+            assert_eq!(pair.add_liquidity(5_000, 5_000, 0, 0), Ok(2_500));
+            assert_eq!(mock.balance_of(A, accounts.bob), 1_000_000 - 1_250);
+            assert_eq!(mock.balance_of(B, accounts.bob), 1_000_000 - 5_000);
+
+            assert_eq!(
+                pair.remove_liquidity(2_501, 0, 0),
+                Err(AmmError::InsufficientLiquidity)
+            );
+            assert_eq!(pair.remove_liquidity(2_500, 0, 0), Ok((1_250, 5_000)));
+            assert_eq!(mock.balance_of(LP, accounts.bob), 0);
+            assert_eq!(mock.balance_of(A, accounts.bob), 1_000_000);
+            assert_eq!(pair.reserves().0, 10_000);
+        }
+
+        #[ink::test]
+        fn swaps_with_fee_and_slippage_limit() {
+            let (mut pair, mock, accounts) = setup();
+            pair.add_liquidity(100_000, 100_000, 0, 0).unwrap();
+
+            mock.set_caller(accounts.bob);
+            // 10_000 in, 9_970 after fee: 9_970 * 100_000 / 109_970
+            assert_eq!(pair.quote(A, 10_000), Ok(9_066));
+            assert_eq!(pair.swap(A, 10_000, 9_067), Err(AmmError::Slippage));
+            assert_eq!(pair.swap(A, 10_000, 9_066), Ok(9_066));
+            assert_eq!(mock.balance_of(B, accounts.bob), 1_000_000 + 9_066);
+            assert_eq!(pair.reserves().0, 110_000);
+            assert_eq!(pair.reserves().1, 100_000 - 9_066);
+            assert_eq!(pair.swap(LP, 1, 0), Err(AmmError::UnknownAsset));
+        }
+
+        #[ink::test]
+        fn accumulates_prices_per_block() {
+            let (mut pair, _, _) = setup();
+            pair.add_liquidity(10_000, 20_000, 0, 0).unwrap();
+            let (price_a, price_b, start) = pair.price_cumulatives();
+
+            for _ in 0..5 {
+                test::advance_block::<DefaultEnvironment>();
+            }
+            let (price_a_now, price_b_now, now) = pair.price_cumulatives();
+            let elapsed = (now - start) as u128;
+            assert_eq!((price_a_now - price_a) / elapsed, 2 * PRICE_SCALE);
+            assert_eq!((price_b_now - price_b) / elapsed, PRICE_SCALE / 2);
+        }
+    }
+}
@@ -0,0 +1,366 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod asset_factory {
+    use assets_extension::*;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use ink::ToAccountId;
+    use openbrush::contracts::traits::ownable::{OwnableError, OwnableRef};
+    use psp22_pallet_wrapper::psp22_pallet_wrapper::PSP22WrapperContractRef;
+
+    /// Maximum number of taken asset ids skipped by a single `create_asset`
+    pub const MAX_ID_ATTEMPTS: u32 = 16;
+
+    #[ink(event)]
+    pub struct AssetCreated {
+        #[ink(topic)]
+        asset_id: u128,
+        #[ink(topic)]
+        wrapper: AccountId,
+        #[ink(topic)]
+        creator: AccountId,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum FactoryError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        /// Error from the wrapper while handing over its ownership
+        Ownable(OwnableError),
+        NotOwner,
+        /// `MAX_ID_ATTEMPTS` consecutive asset ids are taken
+        NoUnusedAssetId,
+        /// The wrapper could not be instantiated from `wrapper_code_hash`
+        InstantiationFailed,
+        /// The transferred value does not cover the asset and metadata deposits
+        InsufficientDeposit,
+        TransferFailed,
+    }
+
+    impl From<AssetsError> for FactoryError {
+        fn from(error: AssetsError) -> Self {
+            FactoryError::Assets(error)
+        }
+    }
+
+    impl From<OwnableError> for FactoryError {
+        fn from(error: OwnableError) -> Self {
+            FactoryError::Ownable(error)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Deployment {
+        pub asset_id: u128,
+        pub wrapper: AccountId,
+        pub creator: AccountId,
+    }
+
+    /// Creates pallet assets together with their `psp22_pallet_wrapper`.
+    ///
+    /// Assets are created with `Origin::Address`, so the factory reserves the asset
+    /// and metadata deposits from its balance and owns the asset until it is handed
+    /// over to the creator. The creator pays the deposits with the value sent to
+    /// `create_asset`.
+    #[ink(storage)]
+    pub struct AssetFactory {
+        owner: AccountId,
+        wrapper_code_hash: Hash,
+        /// Next asset id to try
+        next_asset_id: u128,
+        deployments: Mapping<u128, Deployment>,
+        /// Asset ids in creation order
+        asset_ids: Mapping<u32, u128>,
+        deployments_count: u32,
+    }
+
+    impl AssetFactory {
+        #[ink(constructor)]
+        pub fn new(wrapper_code_hash: Hash, first_asset_id: u128) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                wrapper_code_hash,
+                next_asset_id: first_asset_id,
+                deployments: Mapping::default(),
+                asset_ids: Mapping::default(),
+                deployments_count: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn wrapper_code_hash(&self) -> Hash {
+            self.wrapper_code_hash
+        }
+
+        #[ink(message)]
+        pub fn deployment(&self, asset_id: u128) -> Option<Deployment> {
+            self.deployments.get(asset_id)
+        }
+
+        #[ink(message)]
+        pub fn deployments_count(&self) -> u32 {
+            self.deployments_count
+        }
+
+        /// Deployment number `index`, in creation order
+        #[ink(message)]
+        pub fn deployment_at(&self, index: u32) -> Option<Deployment> {
+            self.asset_ids
+                .get(index)
+                .and_then(|asset_id| self.deployments.get(asset_id))
+        }
+
+        /// Creates an asset with an unused id and the given metadata, with the caller
+        /// as admin, and deploys its wrapper. With `transfer_ownership` the caller
+        /// also becomes owner of the asset and of the wrapper, otherwise the factory
+        /// keeps them.
+        ///
+        /// The transferred value must cover the asset and metadata deposits, the
+        /// rest is sent back to the caller.
+        #[ink(message, payable)]
+        pub fn create_asset(
+            &mut self,
+            name: Vec<u8>,
+            symbol: Vec<u8>,
+            decimals: u8,
+            min_balance: Balance,
+            transfer_ownership: bool,
+        ) -> Result<Deployment, FactoryError> {
+            let creator = self.env().caller();
+            let transferred = self.env().transferred_value();
+            let balance = self.env().balance();
+            let asset_id = self.create_unused(creator, min_balance)?;
+            AssetsExtension::set_metadata(Origin::Address, asset_id, name, symbol, decimals)?;
+            self.refund_excess(creator, transferred, balance)?;
+
+            let wrapper = PSP22WrapperContractRef::new(asset_id)
+                .code_hash(self.wrapper_code_hash)
+                .endowment(0)
+                .salt_bytes(asset_id.to_le_bytes())
+                .try_instantiate()
+                .map_err(|_| FactoryError::InstantiationFailed)?
+                .map_err(|_| FactoryError::InstantiationFailed)?
+                .to_account_id();
+
+            if transfer_ownership {
+                AssetsExtension::transfer_ownership(Origin::Address, asset_id, creator)?;
+                OwnableRef::transfer_ownership(&wrapper, creator)?;
+            }
+
+            let deployment = Deployment {
+                asset_id,
+                wrapper,
+                creator,
+            };
+            self.deployments.insert(asset_id, &deployment);
+            self.asset_ids.insert(self.deployments_count, &asset_id);
+            self.deployments_count += 1;
+            self.env().emit_event(AssetCreated {
+                asset_id,
+                wrapper,
+                creator,
+            });
+            Ok(deployment)
+        }
+
+        /// Wrappers of assets created afterwards are instantiated from `code_hash`
+        #[ink(message)]
+        pub fn set_wrapper_code_hash(&mut self, code_hash: Hash) -> Result<(), FactoryError> {
+            self.ensure_owner()?;
+            self.wrapper_code_hash = code_hash;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), FactoryError> {
+            self.ensure_owner()?;
+            self.owner = new_owner;
+            Ok(())
+        }
+
+        /// Sends `value` of the factory balance to the owner, e.g. deposits released
+        /// by destroyed assets
+        #[ink(message)]
+        pub fn withdraw(&mut self, value: Balance) -> Result<(), FactoryError> {
+            self.ensure_owner()?;
+            self.env()
+                .transfer(self.owner, value)
+                .map_err(|_| FactoryError::TransferFailed)
+        }
+
+        /// Creates the asset with the first free id from `next_asset_id`,
+        /// skipping ids the pallet reports as in use
+        fn create_unused(
+            &mut self,
+            admin: AccountId,
+            min_balance: Balance,
+        ) -> Result<u128, FactoryError> {
+            for _ in 0..MAX_ID_ATTEMPTS {
+                let asset_id = self.next_asset_id;
+                self.next_asset_id += 1;
+                match AssetsExtension::create(Origin::Address, asset_id, admin, min_balance) {
+                    Ok(()) => return Ok(asset_id),
+                    Err(AssetsError::InUse) => continue,
+                    Err(error) => return Err(error.into()),
+                }
+            }
+            Err(FactoryError::NoUnusedAssetId)
+        }
+
+        /// Sends `to` the part of `transferred` not reserved as deposits since the
+        /// factory balance was `balance_before`. Returns the refunded amount.
+        fn refund_excess(
+            &self,
+            to: AccountId,
+            transferred: Balance,
+            balance_before: Balance,
+        ) -> Result<Balance, FactoryError> {
+            let deposits = balance_before.saturating_sub(self.env().balance());
+            let excess = transferred
+                .checked_sub(deposits)
+                .ok_or(FactoryError::InsufficientDeposit)?;
+            if excess > 0 {
+                self.env()
+                    .transfer(to, excess)
+                    .map_err(|_| FactoryError::TransferFailed)?;
+            }
+            Ok(excess)
+        }
+
+        fn ensure_owner(&self) -> Result<(), FactoryError> {
+            if self.env().caller() != self.owner {
+                return Err(FactoryError::NotOwner);
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use assets_extension::mock::AssetsMock;
+        use ink::env::{test, DefaultEnvironment};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+
+        fn setup() -> (AssetFactory, AssetsMock) {
+            let mock = AssetsMock::register(AccountId::from(CONTRACT));
+            (AssetFactory::new(Hash::from([0x01; 32]), 100), mock)
+        }
+
+        #[ink::test]
+        fn taken_asset_ids_are_skipped() {
+            let (mut factory, mock) = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            mock.create(100, accounts.bob, 1);
+            mock.create(101, accounts.bob, 1);
+
+            assert_eq!(factory.create_unused(accounts.alice, 1), Ok(102));
+            let asset = mock.asset(102).unwrap();
+            assert_eq!(asset.owner, AccountId::from(CONTRACT));
+            assert_eq!(asset.admin, accounts.alice);
+            assert_eq!(factory.create_unused(accounts.alice, 1), Ok(103));
+        }
+
+        #[ink::test]
+        fn gives_up_after_max_attempts() {
+            let (mut factory, mock) = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            for asset_id in 100..100 + MAX_ID_ATTEMPTS as u128 {
+                mock.create(asset_id, accounts.bob, 1);
+            }
+
+            assert_eq!(
+                factory.create_unused(accounts.alice, 1),
+                Err(FactoryError::NoUnusedAssetId)
+            );
+            assert_eq!(
+                factory.create_unused(accounts.alice, 0),
+                Err(FactoryError::Assets(AssetsError::MinBalanceZero))
+            );
+        }
+
+        #[ink::test]
+        fn create_asset_keeps_pallet_error() {
+            let (mut factory, _) = setup();
+
+            assert_eq!(
+                factory.create_asset(vec![b'a'; 51], b"A".to_vec(), 12, 1, true),
+                Err(FactoryError::Assets(AssetsError::BadMetadata))
+            );
+            assert_eq!(
+                factory.create_asset(b"Asset".to_vec(), b"A".to_vec(), 12, 0, true),
+                Err(FactoryError::Assets(AssetsError::MinBalanceZero))
+            );
+            assert_eq!(factory.deployments_count(), 0);
+        }
+
+        #[ink::test]
+        fn excess_value_is_refunded() {
+            let (mut factory, _) = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            // the factory already holds 1_000 and the 25 sent with the call
+            test::set_account_balance::<DefaultEnvironment>(AccountId::from(CONTRACT), 1_025);
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            test::set_value_transferred::<DefaultEnvironment>(25);
+
+            // the mock reserves no deposits, so all of the value is sent back before
+            // the wrapper is instantiated, which the off-chain environment cannot do
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                factory.create_asset(b"Asset".to_vec(), b"A".to_vec(), 12, 1, true)
+            }));
+            assert!(result.is_err());
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.bob),
+                Ok(balance + 25)
+            );
+        }
+
+        #[ink::test]
+        fn owner_withdraws() {
+            let (mut factory, _) = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_account_balance::<DefaultEnvironment>(AccountId::from(CONTRACT), 100);
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(factory.withdraw(40), Err(FactoryError::NotOwner));
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(factory.withdraw(40), Ok(()));
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice),
+                Ok(balance + 40)
+            );
+            assert_eq!(factory.withdraw(100), Err(FactoryError::TransferFailed));
+        }
+
+        #[ink::test]
+        fn only_owner_sets_code_hash() {
+            let (mut factory, _) = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                factory.set_wrapper_code_hash(Hash::from([0x02; 32])),
+                Err(FactoryError::NotOwner)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            factory
+                .set_wrapper_code_hash(Hash::from([0x02; 32]))
+                .unwrap();
+            assert_eq!(factory.wrapper_code_hash(), Hash::from([0x02; 32]));
+        }
+    }
+}
@@ -0,0 +1,472 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod asset_registry {
+    use assets_extension::*;
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+
+    /// Maximum number of entries returned by `list`
+    pub const MAX_PAGE_SIZE: u32 = 50;
+
+    #[ink(event)]
+    pub struct Registered {
+        #[ink(topic)]
+        asset_id: u128,
+        symbol: Vec<u8>,
+        wrapper: Option<AccountId>,
+    }
+
+    #[ink(event)]
+    pub struct VerificationChanged {
+        #[ink(topic)]
+        asset_id: u128,
+        #[ink(topic)]
+        curator: AccountId,
+        verified: bool,
+    }
+
+    #[ink(event)]
+    pub struct Removed {
+        #[ink(topic)]
+        asset_id: u128,
+        #[ink(topic)]
+        curator: AccountId,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum RegistryError {
+        NotOwner,
+        NotCurator,
+        /// The asset has no metadata in the pallet
+        NoMetadata,
+        /// The symbol differs from the pallet metadata of the asset
+        SymbolMismatch,
+        /// A verified listing has the symbol
+        SymbolTaken,
+        /// A verified listing has the wrapper
+        WrapperTaken,
+        /// The wrapper does not wrap the asset
+        WrapperMismatch,
+        AlreadyRegistered,
+        NotRegistered,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Entry {
+        pub asset_id: u128,
+        pub symbol: Vec<u8>,
+        pub decimals: u8,
+        /// PSP22 contract of the asset, e.g. a `psp22_pallet_wrapper`
+        pub wrapper: Option<AccountId>,
+        /// Set by a curator who checked the listing
+        pub verified: bool,
+    }
+
+    /// Registry of pallet assets by symbol and wrapper address.
+    ///
+    /// Anyone can register an asset whose pallet metadata matches the given symbol,
+    /// curators verify listings and remove squatted or stale ones. Only verified
+    /// listings are found by symbol or wrapper, so unverified ones may share a symbol
+    /// until a curator verifies one of them.
+    #[ink(storage)]
+    pub struct AssetRegistry {
+        owner: AccountId,
+        curators: Mapping<AccountId, ()>,
+        entries: Mapping<u128, Entry>,
+        /// Verified listings by symbol
+        by_symbol: Mapping<Vec<u8>, u128>,
+        /// Verified listings by wrapper
+        by_wrapper: Mapping<AccountId, u128>,
+        /// Registered asset ids, listed by index
+        asset_ids: Mapping<u32, u128>,
+        /// Index of each registered asset id in `asset_ids`
+        indices: Mapping<u128, u32>,
+        count: u32,
+    }
+
+    impl Default for AssetRegistry {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl AssetRegistry {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            let owner = Self::env().caller();
+            let mut curators = Mapping::default();
+            curators.insert(owner, &());
+            Self {
+                owner,
+                curators,
+                entries: Mapping::default(),
+                by_symbol: Mapping::default(),
+                by_wrapper: Mapping::default(),
+                asset_ids: Mapping::default(),
+                indices: Mapping::default(),
+                count: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn is_curator(&self, account: AccountId) -> bool {
+            self.curators.contains(account)
+        }
+
+        #[ink(message)]
+        pub fn entry(&self, asset_id: u128) -> Option<Entry> {
+            self.entries.get(asset_id)
+        }
+
+        #[ink(message)]
+        pub fn asset_by_symbol(&self, symbol: Vec<u8>) -> Option<u128> {
+            self.by_symbol.get(symbol)
+        }
+
+        #[ink(message)]
+        pub fn asset_by_wrapper(&self, wrapper: AccountId) -> Option<u128> {
+            self.by_wrapper.get(wrapper)
+        }
+
+        #[ink(message)]
+        pub fn count(&self) -> u32 {
+            self.count
+        }
+
+        /// Up to `limit` entries from index `start`, at most `MAX_PAGE_SIZE`.
+        /// Removing an entry moves the last one to its index.
+        #[ink(message)]
+        pub fn list(&self, start: u32, limit: u32) -> Vec<Entry> {
+            let end = start
+                .saturating_add(limit.min(MAX_PAGE_SIZE))
+                .min(self.count);
+            (start..end)
+                .filter_map(|index| self.asset_ids.get(index))
+                .filter_map(|asset_id| self.entries.get(asset_id))
+                .collect()
+        }
+
+        /// Registers `asset_id` under `symbol`, which must match the pallet metadata.
+        /// `wrapper` must return `asset_id` from its `asset_id` message.
+        #[ink(message)]
+        pub fn register(
+            &mut self,
+            asset_id: u128,
+            symbol: Vec<u8>,
+            wrapper: Option<AccountId>,
+        ) -> Result<(), RegistryError> {
+            if self.entries.contains(asset_id) {
+                return Err(RegistryError::AlreadyRegistered);
+            }
+            let decimals = Self::validate(asset_id, &symbol)?;
+            if self.by_symbol.contains(&symbol) {
+                return Err(RegistryError::SymbolTaken);
+            }
+            if let Some(wrapper) = wrapper {
+                if self.by_wrapper.contains(wrapper) {
+                    return Err(RegistryError::WrapperTaken);
+                }
+                if Self::wrapped_asset(wrapper) != Some(asset_id) {
+                    return Err(RegistryError::WrapperMismatch);
+                }
+            }
+            self.entries.insert(
+                asset_id,
+                &Entry {
+                    asset_id,
+                    symbol: symbol.clone(),
+                    decimals,
+                    wrapper,
+                    verified: false,
+                },
+            );
+            self.asset_ids.insert(self.count, &asset_id);
+            self.indices.insert(asset_id, &self.count);
+            self.count += 1;
+            self.env().emit_event(Registered {
+                asset_id,
+                symbol,
+                wrapper,
+            });
+            Ok(())
+        }
+
+        /// Marks the listing as verified once its metadata is checked again,
+        /// which makes it the one found by its symbol and wrapper, or unverifies it
+        #[ink(message)]
+        pub fn set_verified(
+            &mut self,
+            asset_id: u128,
+            verified: bool,
+        ) -> Result<(), RegistryError> {
+            let curator = self.ensure_curator()?;
+            let mut entry = self
+                .entries
+                .get(asset_id)
+                .ok_or(RegistryError::NotRegistered)?;
+            if verified {
+                entry.decimals = Self::validate(asset_id, &entry.symbol)?;
+                if !entry.verified {
+                    self.insert_lookups(&entry)?;
+                }
+            } else if entry.verified {
+                self.remove_lookups(&entry);
+            }
+            entry.verified = verified;
+            self.entries.insert(asset_id, &entry);
+            self.env().emit_event(VerificationChanged {
+                asset_id,
+                curator,
+                verified,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn remove(&mut self, asset_id: u128) -> Result<(), RegistryError> {
+            let curator = self.ensure_curator()?;
+            let entry = self
+                .entries
+                .take(asset_id)
+                .ok_or(RegistryError::NotRegistered)?;
+            if entry.verified {
+                self.remove_lookups(&entry);
+            }
+            let index = self.indices.take(asset_id).unwrap_or_default();
+            self.count -= 1;
+            if index != self.count {
+                let last = self.asset_ids.get(self.count).unwrap_or_default();
+                self.asset_ids.insert(index, &last);
+                self.indices.insert(last, &index);
+            }
+            self.asset_ids.remove(self.count);
+            self.env().emit_event(Removed { asset_id, curator });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn add_curator(&mut self, curator: AccountId) -> Result<(), RegistryError> {
+            self.ensure_owner()?;
+            self.curators.insert(curator, &());
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn remove_curator(&mut self, curator: AccountId) -> Result<(), RegistryError> {
+            self.ensure_owner()?;
+            self.curators.remove(curator);
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), RegistryError> {
+            self.ensure_owner()?;
+            self.owner = new_owner;
+            Ok(())
+        }
+
+        /// Returns the decimals of the asset if its pallet symbol is `symbol`
+        fn validate(asset_id: u128, symbol: &[u8]) -> Result<u8, RegistryError> {
+            let metadata_symbol = AssetsExtension::metadata_symbol(asset_id);
+            if metadata_symbol.is_empty() {
+                return Err(RegistryError::NoMetadata);
+            }
+            if metadata_symbol != symbol {
+                return Err(RegistryError::SymbolMismatch);
+            }
+            Ok(AssetsExtension::metadata_decimals(asset_id))
+        }
+
+        /// Asset id returned by the `asset_id` message of `wrapper`, if it has one
+        fn wrapped_asset(wrapper: AccountId) -> Option<u128> {
+            build_call::<Environment>()
+                .call(wrapper)
+                .exec_input(ExecutionInput::new(Selector::new(ink::selector_bytes!(
+                    "asset_id"
+                ))))
+                .returns::<u128>()
+                .try_invoke()
+                .ok()?
+                .ok()
+        }
+
+        fn insert_lookups(&mut self, entry: &Entry) -> Result<(), RegistryError> {
+            if self.by_symbol.contains(&entry.symbol) {
+                return Err(RegistryError::SymbolTaken);
+            }
+            if let Some(wrapper) = entry.wrapper {
+                if self.by_wrapper.contains(wrapper) {
+                    return Err(RegistryError::WrapperTaken);
+                }
+                self.by_wrapper.insert(wrapper, &entry.asset_id);
+            }
+            self.by_symbol.insert(&entry.symbol, &entry.asset_id);
+            Ok(())
+        }
+
+        fn remove_lookups(&mut self, entry: &Entry) {
+            self.by_symbol.remove(&entry.symbol);
+            if let Some(wrapper) = entry.wrapper {
+                self.by_wrapper.remove(wrapper);
+            }
+        }
+
+        fn ensure_owner(&self) -> Result<(), RegistryError> {
+            if self.env().caller() != self.owner {
+                return Err(RegistryError::NotOwner);
+            }
+            Ok(())
+        }
+
+        fn ensure_curator(&self) -> Result<AccountId, RegistryError> {
+            let caller = self.env().caller();
+            if !self.curators.contains(caller) {
+                return Err(RegistryError::NotCurator);
+            }
+            Ok(caller)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use assets_extension::mock::AssetsMock;
+        use ink::env::{test, DefaultEnvironment};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+
+        fn setup() -> (
+            AssetRegistry,
+            AssetsMock,
+            test::DefaultAccounts<DefaultEnvironment>,
+        ) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mock = AssetsMock::register(AccountId::from(CONTRACT));
+            for (asset_id, symbol) in [(1, b"ASTR"), (2, b"USDT"), (3, b"WBTC")] {
+                mock.create(asset_id, accounts.alice, 1);
+                mock.set_metadata(asset_id, b"Token", symbol, 18);
+            }
+            (AssetRegistry::new(), mock, accounts)
+        }
+
+        #[ink::test]
+        fn register_validates_metadata() {
+            let (mut registry, mock, accounts) = setup();
+            mock.create(4, accounts.alice, 1);
+
+            assert_eq!(
+                registry.register(1, b"USDT".to_vec(), None),
+                Err(RegistryError::SymbolMismatch)
+            );
+            assert_eq!(
+                registry.register(4, b"NONE".to_vec(), None),
+                Err(RegistryError::NoMetadata)
+            );
+            assert_eq!(registry.register(1, b"ASTR".to_vec(), None), Ok(()));
+            assert_eq!(
+                registry.register(1, b"ASTR".to_vec(), None),
+                Err(RegistryError::AlreadyRegistered)
+            );
+
+            let entry = registry.entry(1).unwrap();
+            assert_eq!(entry.decimals, 18);
+            assert!(!entry.verified);
+        }
+
+        #[ink::test]
+        fn lookups_only_find_verified_listings() {
+            let (mut registry, mock, accounts) = setup();
+            // anyone can create an asset with a taken symbol
+            mock.create(4, accounts.eve, 1);
+            mock.set_metadata(4, b"Token", b"ASTR", 18);
+            test::set_caller::<DefaultEnvironment>(accounts.eve);
+            registry.register(4, b"ASTR".to_vec(), None).unwrap();
+            registry.register(1, b"ASTR".to_vec(), None).unwrap();
+            assert_eq!(registry.asset_by_symbol(b"ASTR".to_vec()), None);
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            registry.set_verified(1, true).unwrap();
+            assert_eq!(registry.asset_by_symbol(b"ASTR".to_vec()), Some(1));
+            assert_eq!(
+                registry.set_verified(4, true),
+                Err(RegistryError::SymbolTaken)
+            );
+            mock.create(5, accounts.eve, 1);
+            mock.set_metadata(5, b"Token", b"ASTR", 18);
+            assert_eq!(
+                registry.register(5, b"ASTR".to_vec(), None),
+                Err(RegistryError::SymbolTaken)
+            );
+
+            registry.set_verified(1, false).unwrap();
+            assert_eq!(registry.asset_by_symbol(b"ASTR".to_vec()), None);
+            registry.set_verified(4, true).unwrap();
+            registry.remove(1).unwrap();
+            assert_eq!(registry.asset_by_symbol(b"ASTR".to_vec()), Some(4));
+        }
+
+        #[ink::test]
+        fn curators_verify_listings() {
+            let (mut registry, mock, accounts) = setup();
+            registry.register(1, b"ASTR".to_vec(), None).unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                registry.set_verified(1, true),
+                Err(RegistryError::NotCurator)
+            );
+            assert_eq!(
+                registry.add_curator(accounts.bob),
+                Err(RegistryError::NotOwner)
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            registry.add_curator(accounts.bob).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            mock.set_metadata(1, b"Token", b"ASTR", 12);
+            assert_eq!(registry.set_verified(1, true), Ok(()));
+            let entry = registry.entry(1).unwrap();
+            assert!(entry.verified);
+            assert_eq!(entry.decimals, 12);
+
+            mock.set_metadata(1, b"Token", b"FAKE", 12);
+            assert_eq!(
+                registry.set_verified(1, true),
+                Err(RegistryError::SymbolMismatch)
+            );
+        }
+
+        #[ink::test]
+        fn list_is_paginated() {
+            let (mut registry, _, _) = setup();
+            registry.register(1, b"ASTR".to_vec(), None).unwrap();
+            registry.register(2, b"USDT".to_vec(), None).unwrap();
+            registry.register(3, b"WBTC".to_vec(), None).unwrap();
+
+            let ids = |entries: Vec<Entry>| -> Vec<u128> {
+                entries.into_iter().map(|entry| entry.asset_id).collect()
+            };
+            assert_eq!(ids(registry.list(0, 2)), [1, 2]);
+            assert_eq!(ids(registry.list(2, 2)), [3]);
+            assert_eq!(ids(registry.list(5, 2)), Vec::<u128>::new());
+
+            registry.remove(1).unwrap();
+            assert_eq!(registry.count(), 2);
+            assert_eq!(ids(registry.list(0, 10)), [3, 2]);
+            assert_eq!(registry.asset_by_symbol(b"ASTR".to_vec()), None);
+            assert_eq!(registry.remove(1), Err(RegistryError::NotRegistered));
+        }
+    }
+}
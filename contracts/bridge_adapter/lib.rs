@@ -0,0 +1,522 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod bridge_adapter {
+    use assets_extension::*;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+
+    /// Maximum number of relayers
+    pub const MAX_RELAYERS: u32 = 32;
+    /// Maximum length of a recipient address on the remote chain
+    pub const MAX_RECIPIENT_LEN: u32 = 64;
+    /// Prefix of inbound messages signed by the relayers
+    pub const INBOUND_PREFIX: &[u8] = b"bridge_adapter::inbound";
+
+    /// How an asset is moved across the bridge
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Mode {
+        /// Outbound assets are locked in the contract and unlocked on the way back
+        Lock,
+        /// Outbound assets are burned and minted on the way back. The contract
+        /// must be the admin of the asset.
+        Burn,
+    }
+
+    /// Transfer from the remote chain, signed by the relayers
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct InboundMessage {
+        pub source_chain: u32,
+        /// Nonce of the transfer on the source chain
+        pub nonce: u64,
+        pub asset_id: u128,
+        pub recipient: AccountId,
+        pub amount: Balance,
+    }
+
+    /// Transfer to the remote chain, picked up by the relayers
+    #[ink(event)]
+    pub struct BridgedOut {
+        #[ink(topic)]
+        nonce: u64,
+        #[ink(topic)]
+        asset_id: u128,
+        #[ink(topic)]
+        sender: AccountId,
+        amount: Balance,
+        mode: Mode,
+        dest_chain: u32,
+        /// Encoded address on the remote chain
+        recipient: Vec<u8>,
+    }
+
+    #[ink(event)]
+    pub struct BridgedIn {
+        #[ink(topic)]
+        source_chain: u32,
+        #[ink(topic)]
+        nonce: u64,
+        #[ink(topic)]
+        recipient: AccountId,
+        asset_id: u128,
+        amount: Balance,
+        mode: Mode,
+    }
+
+    #[ink(event)]
+    pub struct RelayersChanged {
+        relayers: Vec<AccountId>,
+        threshold: u32,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum BridgeError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        NotOwner,
+        /// Relayers are empty, more than `MAX_RELAYERS` or not unique
+        InvalidRelayers,
+        /// Threshold is zero or above the number of relayers
+        InvalidThreshold,
+        /// The asset is not enabled on the bridge
+        UnsupportedAsset,
+        ZeroAmount,
+        /// Recipient is empty or longer than `MAX_RECIPIENT_LEN`
+        InvalidRecipient,
+        /// The inbound message was already processed
+        AlreadyProcessed,
+        /// Fewer than `threshold` relayers signed the inbound message
+        NotEnoughSignatures,
+        /// More signatures than relayers were submitted
+        TooManySignatures,
+    }
+
+    impl From<AssetsError> for BridgeError {
+        fn from(error: AssetsError) -> Self {
+            BridgeError::Assets(error)
+        }
+    }
+
+    /// Lock-and-mint bridge endpoint for pallet assets.
+    ///
+    /// Outbound transfers lock or burn the assets of the caller and emit a
+    /// `BridgedOut` event with a sequential nonce, which off-chain relayers
+    /// deliver to the remote chain. Inbound transfers are submitted by anyone
+    /// with the ecdsa signatures of at least `threshold` relayers, and each
+    /// `(source_chain, nonce)` is processed once.
+    #[ink(storage)]
+    pub struct BridgeAdapter {
+        owner: AccountId,
+        /// Accounts of the relayer ecdsa keys, i.e. the blake2 hash of the
+        /// compressed public keys
+        relayers: Vec<AccountId>,
+        threshold: u32,
+        assets: Mapping<u128, Mode>,
+        next_nonce: u64,
+        processed: Mapping<(u32, u64), ()>,
+    }
+
+    impl BridgeAdapter {
+        #[ink(constructor)]
+        pub fn new(relayers: Vec<AccountId>, threshold: u32) -> Result<Self, BridgeError> {
+            Self::check_relayers(&relayers, threshold)?;
+            Ok(Self {
+                owner: Self::env().caller(),
+                relayers,
+                threshold,
+                assets: Mapping::default(),
+                next_nonce: 0,
+                processed: Mapping::default(),
+            })
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn relayers(&self) -> Vec<AccountId> {
+            self.relayers.clone()
+        }
+
+        #[ink(message)]
+        pub fn threshold(&self) -> u32 {
+            self.threshold
+        }
+
+        /// Mode of `asset_id`, `None` if it is not enabled
+        #[ink(message)]
+        pub fn asset_mode(&self, asset_id: u128) -> Option<Mode> {
+            self.assets.get(asset_id)
+        }
+
+        /// Nonce of the next outbound transfer
+        #[ink(message)]
+        pub fn next_nonce(&self) -> u64 {
+            self.next_nonce
+        }
+
+        #[ink(message)]
+        pub fn is_processed(&self, source_chain: u32, nonce: u64) -> bool {
+            self.processed.contains((source_chain, nonce))
+        }
+
+        /// Enables `asset_id` with `mode`, or disables it with `None`
+        #[ink(message)]
+        pub fn set_asset(&mut self, asset_id: u128, mode: Option<Mode>) -> Result<(), BridgeError> {
+            self.ensure_owner()?;
+            match mode {
+                Some(mode) => {
+                    self.assets.insert(asset_id, &mode);
+                }
+                None => self.assets.remove(asset_id),
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_relayers(
+            &mut self,
+            relayers: Vec<AccountId>,
+            threshold: u32,
+        ) -> Result<(), BridgeError> {
+            self.ensure_owner()?;
+            Self::check_relayers(&relayers, threshold)?;
+            self.relayers = relayers.clone();
+            self.threshold = threshold;
+            self.env().emit_event(RelayersChanged {
+                relayers,
+                threshold,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), BridgeError> {
+            self.ensure_owner()?;
+            self.owner = new_owner;
+            Ok(())
+        }
+
+        /// Sends `amount` of `asset_id` to `recipient` on `dest_chain`. In
+        /// `Lock` mode the caller must have approved the contract to transfer
+        /// `amount`, in `Burn` mode it must hold `amount` as the pallet burns
+        /// only up to the balance.
+        #[ink(message)]
+        pub fn bridge_out(
+            &mut self,
+            asset_id: u128,
+            amount: Balance,
+            dest_chain: u32,
+            recipient: Vec<u8>,
+        ) -> Result<u64, BridgeError> {
+            let mode = self
+                .asset_mode(asset_id)
+                .ok_or(BridgeError::UnsupportedAsset)?;
+            if amount == 0 {
+                return Err(BridgeError::ZeroAmount);
+            }
+            if recipient.is_empty() || recipient.len() as u32 > MAX_RECIPIENT_LEN {
+                return Err(BridgeError::InvalidRecipient);
+            }
+            let sender = self.env().caller();
+            match mode {
+                Mode::Lock => AssetsExtension::transfer_approved(
+                    Origin::Address,
+                    asset_id,
+                    sender,
+                    self.env().account_id(),
+                    amount,
+                )?,
+                Mode::Burn => {
+                    if AssetsExtension::balance_of(asset_id, sender) < amount {
+                        return Err(AssetsError::BalanceLow.into());
+                    }
+                    AssetsExtension::burn(Origin::Address, asset_id, sender, amount)?
+                }
+            }
+            let nonce = self.next_nonce;
+            self.next_nonce += 1;
+            self.env().emit_event(BridgedOut {
+                nonce,
+                asset_id,
+                sender,
+                amount,
+                mode,
+                dest_chain,
+                recipient,
+            });
+            Ok(nonce)
+        }
+
+        /// Message the relayers sign for `message`, the SCALE encoding of
+        /// `(INBOUND_PREFIX, contract, message)`
+        #[ink(message)]
+        pub fn inbound_payload(&self, message: InboundMessage) -> Vec<u8> {
+            scale::Encode::encode(&(INBOUND_PREFIX, self.env().account_id(), message))
+        }
+
+        /// Unlocks or mints an inbound transfer signed by at least `threshold`
+        /// relayers. Signatures of unknown keys and repeated signers are
+        /// ignored, but there can be no more signatures than relayers. Anyone
+        /// can submit the message.
+        #[ink(message)]
+        pub fn bridge_in(
+            &mut self,
+            message: InboundMessage,
+            signatures: Vec<[u8; 65]>,
+        ) -> Result<(), BridgeError> {
+            let mode = self
+                .asset_mode(message.asset_id)
+                .ok_or(BridgeError::UnsupportedAsset)?;
+            let key = (message.source_chain, message.nonce);
+            if self.processed.contains(key) {
+                return Err(BridgeError::AlreadyProcessed);
+            }
+            if signatures.len() > self.relayers.len() {
+                return Err(BridgeError::TooManySignatures);
+            }
+            let payload = self.inbound_payload(message.clone());
+            let mut signers = Vec::new();
+            for signature in signatures.iter() {
+                if let Some(signer) = ecdsa_signer::signer(&payload, signature) {
+                    if self.relayers.contains(&signer) && !signers.contains(&signer) {
+                        signers.push(signer);
+                    }
+                }
+            }
+            if (signers.len() as u32) < self.threshold {
+                return Err(BridgeError::NotEnoughSignatures);
+            }
+            match mode {
+                Mode::Lock => AssetsExtension::transfer(
+                    Origin::Address,
+                    message.asset_id,
+                    message.recipient,
+                    message.amount,
+                )?,
+                Mode::Burn => AssetsExtension::mint(
+                    Origin::Address,
+                    message.asset_id,
+                    message.recipient,
+                    message.amount,
+                )?,
+            }
+            self.processed.insert(key, &());
+            self.env().emit_event(BridgedIn {
+                source_chain: message.source_chain,
+                nonce: message.nonce,
+                recipient: message.recipient,
+                asset_id: message.asset_id,
+                amount: message.amount,
+                mode,
+            });
+            Ok(())
+        }
+
+        fn check_relayers(relayers: &[AccountId], threshold: u32) -> Result<(), BridgeError> {
+            if relayers.is_empty()
+                || relayers.len() as u32 > MAX_RELAYERS
+                || relayers
+                    .iter()
+                    .enumerate()
+                    .any(|(index, relayer)| relayers[..index].contains(relayer))
+            {
+                return Err(BridgeError::InvalidRelayers);
+            }
+            if threshold == 0 || threshold > relayers.len() as u32 {
+                return Err(BridgeError::InvalidThreshold);
+            }
+            Ok(())
+        }
+
+        fn ensure_owner(&self) -> Result<(), BridgeError> {
+            if self.env().caller() != self.owner {
+                return Err(BridgeError::NotOwner);
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use assets_extension::mock::AssetsMock;
+        use ecdsa_signer::{sign, SecretKey};
+        use ink::env::{test, DefaultEnvironment};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const LOCKED: u128 = 1;
+        const WRAPPED: u128 = 2;
+        const REMOTE_CHAIN: u32 = 7;
+
+        fn relayer_key(index: u8) -> SecretKey {
+            SecretKey::from_slice(&[0x11 + index; 32]).unwrap()
+        }
+
+        fn relayer(index: u8) -> AccountId {
+            ecdsa_signer::account(&relayer_key(index))
+        }
+
+        fn setup() -> (
+            BridgeAdapter,
+            AssetsMock,
+            test::DefaultAccounts<DefaultEnvironment>,
+        ) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mock = AssetsMock::register(AccountId::from(CONTRACT));
+            mock.create(LOCKED, accounts.alice, 1);
+            mock.create(WRAPPED, AccountId::from(CONTRACT), 1);
+            mock.set_caller(accounts.alice);
+            let mut bridge =
+                BridgeAdapter::new(vec![relayer(0), relayer(1), relayer(2)], 2).unwrap();
+            bridge.set_asset(LOCKED, Some(Mode::Lock)).unwrap();
+            bridge.set_asset(WRAPPED, Some(Mode::Burn)).unwrap();
+            (bridge, mock, accounts)
+        }
+
+        fn inbound(asset_id: u128, nonce: u64, recipient: AccountId) -> InboundMessage {
+            InboundMessage {
+                source_chain: REMOTE_CHAIN,
+                nonce,
+                asset_id,
+                recipient,
+                amount: 300,
+            }
+        }
+
+        fn signatures(
+            bridge: &BridgeAdapter,
+            message: &InboundMessage,
+            keys: &[u8],
+        ) -> Vec<[u8; 65]> {
+            let payload = bridge.inbound_payload(message.clone());
+            keys.iter()
+                .map(|index| sign(&relayer_key(*index), &payload))
+                .collect()
+        }
+
+        #[ink::test]
+        fn locks_and_unlocks() {
+            let (mut bridge, mock, accounts) = setup();
+            let contract_id = AccountId::from(CONTRACT);
+            mock.mint(LOCKED, accounts.bob, 1_000);
+            mock.approve(LOCKED, accounts.bob, contract_id, 1_000);
+
+            mock.set_caller(accounts.bob);
+            assert_eq!(
+                bridge.bridge_out(LOCKED, 1_000, REMOTE_CHAIN, vec![0xAB; 20]),
+                Ok(0)
+            );
+            assert_eq!(mock.balance_of(LOCKED, contract_id), 1_000);
+            assert_eq!(mock.balance_of(LOCKED, accounts.bob), 0);
+            assert_eq!(bridge.next_nonce(), 1);
+            assert_eq!(test::recorded_events().count(), 1);
+
+            // submitted by anyone
+            mock.set_caller(accounts.eve);
+            let message = inbound(LOCKED, 0, accounts.charlie);
+            let signatures = signatures(&bridge, &message, &[0, 2]);
+            assert_eq!(
+                bridge.bridge_in(message.clone(), signatures.clone()),
+                Ok(())
+            );
+            assert_eq!(mock.balance_of(LOCKED, accounts.charlie), 300);
+            assert_eq!(mock.balance_of(LOCKED, contract_id), 700);
+            assert!(bridge.is_processed(REMOTE_CHAIN, 0));
+            assert_eq!(
+                bridge.bridge_in(message, signatures),
+                Err(BridgeError::AlreadyProcessed)
+            );
+        }
+
+        #[ink::test]
+        fn burns_and_mints() {
+            let (mut bridge, mock, accounts) = setup();
+            mock.set_caller(AccountId::from(CONTRACT));
+            mock.mint(WRAPPED, accounts.bob, 500);
+
+            mock.set_caller(accounts.bob);
+            assert_eq!(
+                bridge.bridge_out(WRAPPED, 200, REMOTE_CHAIN, vec![0xAB; 20]),
+                Ok(0)
+            );
+            assert_eq!(mock.balance_of(WRAPPED, accounts.bob), 300);
+            assert_eq!(mock.asset(WRAPPED).unwrap().supply, 300);
+            // the pallet would burn the 300 held and bridge 400
+            assert_eq!(
+                bridge.bridge_out(WRAPPED, 400, REMOTE_CHAIN, vec![0xAB; 20]),
+                Err(BridgeError::Assets(AssetsError::BalanceLow))
+            );
+            assert_eq!(mock.balance_of(WRAPPED, accounts.bob), 300);
+            assert_eq!(bridge.next_nonce(), 1);
+
+            let message = inbound(WRAPPED, 4, accounts.charlie);
+            let signatures = signatures(&bridge, &message, &[1, 0]);
+            assert_eq!(bridge.bridge_in(message, signatures), Ok(()));
+            assert_eq!(mock.balance_of(WRAPPED, accounts.charlie), 300);
+            assert!(!bridge.is_processed(REMOTE_CHAIN, 3));
+        }
+
+        #[ink::test]
+        fn requires_threshold_of_relayers() {
+            let (mut bridge, mock, accounts) = setup();
+            let message = inbound(WRAPPED, 0, accounts.charlie);
+
+            // the same relayer twice and an unknown key
+            let mut forged = signatures(&bridge, &message, &[1, 1]);
+            let payload = bridge.inbound_payload(message.clone());
+            forged.push(sign(&SecretKey::from_slice(&[0x99; 32]).unwrap(), &payload));
+            assert_eq!(
+                bridge.bridge_in(message.clone(), forged),
+                Err(BridgeError::NotEnoughSignatures)
+            );
+
+            // valid signatures after junk ones are not dropped, too many are rejected
+            let mut padded = vec![[0; 65]];
+            padded.extend(signatures(&bridge, &message, &[0, 1]));
+            assert_eq!(bridge.bridge_in(message.clone(), padded.clone()), Ok(()));
+            assert_eq!(mock.balance_of(WRAPPED, accounts.charlie), 300);
+            padded.push([0; 65]);
+            let mut next = message.clone();
+            next.nonce = 1;
+            assert_eq!(
+                bridge.bridge_in(next, padded),
+                Err(BridgeError::TooManySignatures)
+            );
+
+            // signatures of another message
+            let mut other = inbound(WRAPPED, 2, accounts.charlie);
+            other.amount = 1_000;
+            let signatures = signatures(&bridge, &message, &[0, 1]);
+            assert_eq!(
+                bridge.bridge_in(other, signatures),
+                Err(BridgeError::NotEnoughSignatures)
+            );
+            assert_eq!(mock.balance_of(WRAPPED, accounts.charlie), 300);
+
+            assert_eq!(
+                bridge.bridge_out(LOCKED, 1, REMOTE_CHAIN, Vec::new()),
+                Err(BridgeError::InvalidRecipient)
+            );
+            mock.set_caller(accounts.bob);
+            assert_eq!(
+                bridge.set_relayers(vec![relayer(0)], 1),
+                Err(BridgeError::NotOwner)
+            );
+            mock.set_caller(accounts.alice);
+            assert_eq!(
+                bridge.set_relayers(vec![relayer(0)], 2),
+                Err(BridgeError::InvalidThreshold)
+            );
+            assert_eq!(bridge.set_relayers(vec![relayer(0)], 1), Ok(()));
+        }
+    }
+}
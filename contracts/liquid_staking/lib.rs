@@ -0,0 +1,677 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+#[openbrush::contract]
+pub mod liquid_staking {
+    use dapps_staking_extension::*;
+    use ink::codegen::{EmitEvent, Env};
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use openbrush::{
+        contracts::psp22::extensions::metadata::*,
+        traits::{Storage, String},
+    };
+    use sp_arithmetic::helpers_128bit::multiply_by_rational_with_rounding;
+    use sp_arithmetic::Rounding;
+
+    /// Maximum number of pending unbond requests per account
+    pub const MAX_UNBOND_REQUESTS: u32 = 16;
+
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Staked {
+        #[ink(topic)]
+        staker: AccountId,
+        value: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct RewardsClaimed {
+        reward: Balance,
+    }
+
+    #[ink(event)]
+    pub struct UnbondRequested {
+        #[ink(topic)]
+        staker: AccountId,
+        shares: Balance,
+        value: Balance,
+        /// Era of the batch the value is unbonded with
+        era: u32,
+    }
+
+    #[ink(event)]
+    pub struct Unbonded {
+        era: u32,
+        value: Balance,
+        unlock_era: u32,
+    }
+
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        staker: AccountId,
+        value: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum LiquidStakingError {
+        /// Error from the share token
+        PSP22(PSP22Error),
+        /// Error from the dapps-staking pallet
+        DappsStaking(DSError),
+        /// Staked or unbonded value is zero
+        ZeroAmount,
+        /// Shares are worth less than one unit of the native token
+        AmountTooSmall,
+        /// Too many pending unbond requests, withdraw matured ones first
+        TooManyUnbondRequests,
+        /// The stake left on the dApp would fall below the pallet minimum
+        BelowMinimumStake,
+        /// No unbond request of the caller has matured yet
+        NothingToWithdraw,
+        /// Native transfer to the staker failed
+        TransferFailed,
+        Overflow,
+    }
+
+    impl From<PSP22Error> for LiquidStakingError {
+        fn from(error: PSP22Error) -> Self {
+            LiquidStakingError::PSP22(error)
+        }
+    }
+
+    impl From<DSError> for LiquidStakingError {
+        fn from(error: DSError) -> Self {
+            LiquidStakingError::DappsStaking(error)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct UnbondRequest {
+        pub value: Balance,
+        /// Era of the request, its batch is unbonded in a later era
+        pub era: u32,
+    }
+
+    impl psp22::Internal for LiquidStaking {
+        fn _emit_transfer_event(
+            &self,
+            from: Option<AccountId>,
+            to: Option<AccountId>,
+            amount: Balance,
+        ) {
+            self.env().emit_event(Transfer {
+                from,
+                to,
+                value: amount,
+            });
+        }
+
+        fn _emit_approval_event(&self, owner: AccountId, spender: AccountId, amount: Balance) {
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value: amount,
+            });
+        }
+    }
+
+    /// Stakes native tokens on `dapp` on behalf of its callers and issues shares of the pool.
+    ///
+    /// Rewards are restaked on `dapp` when claimed (the pallet default reward destination),
+    /// so the value of a share grows as `claim_staker` is called each era.
+    ///
+    /// Unbond requests of an era are unbonded together in a later era, so the pallet keeps
+    /// one unlocking chunk per era for the contract however many holders redeem.
+    #[ink(storage)]
+    #[derive(Default, Storage)]
+    pub struct LiquidStaking {
+        #[storage_field]
+        psp22: psp22::Data,
+        #[storage_field]
+        metadata: metadata::Data,
+        dapp: AccountId,
+        /// `MinimumStakingAmount` of the pallet
+        minimum_stake: Balance,
+        unbond_requests: Mapping<AccountId, Vec<UnbondRequest>>,
+        total_unbonding: Balance,
+        /// Value requested in `queued_era`, still staked until `unbond_queued`
+        queued: Balance,
+        queued_era: u32,
+        /// Unlock era of the batch of each era, once unbonded
+        unlock_eras: Mapping<u32, u32>,
+    }
+
+    impl PSP22 for LiquidStaking {}
+
+    impl PSP22Metadata for LiquidStaking {}
+
+    impl LiquidStaking {
+        #[ink(constructor)]
+        pub fn new(
+            dapp: AccountId,
+            minimum_stake: Balance,
+            name: Option<String>,
+            symbol: Option<String>,
+        ) -> Self {
+            let mut instance = Self::default();
+            instance.dapp = dapp;
+            instance.minimum_stake = minimum_stake;
+            instance.metadata.name = name;
+            instance.metadata.symbol = symbol;
+            instance.metadata.decimals = 18;
+            instance
+        }
+
+        #[ink(message)]
+        pub fn dapp(&self) -> AccountId {
+            self.dapp
+        }
+
+        #[ink(message)]
+        pub fn minimum_stake(&self) -> Balance {
+            self.minimum_stake
+        }
+
+        /// Native value currently staked by the pool, including restaked rewards
+        /// and without the value queued for unbonding
+        #[ink(message)]
+        pub fn total_pooled(&self) -> Balance {
+            DappsStaking::read_staked_amount_on_contract(self.env().account_id(), self.dapp)
+                .saturating_sub(self.queued)
+        }
+
+        /// Value waiting in unbond requests, queued or unbonding, not yet paid out
+        #[ink(message)]
+        pub fn total_unbonding(&self) -> Balance {
+            self.total_unbonding
+        }
+
+        /// Native value `shares` can currently be redeemed for
+        #[ink(message)]
+        pub fn shares_to_value(&self, shares: Balance) -> Balance {
+            let supply = self.total_supply();
+            if supply == 0 {
+                return shares;
+            }
+            multiply_by_rational_with_rounding(shares, self.total_pooled(), supply, Rounding::Down)
+                .unwrap_or(Balance::MAX)
+        }
+
+        /// Shares minted for staking `value` at the current exchange rate
+        #[ink(message)]
+        pub fn value_to_shares(&self, value: Balance) -> Balance {
+            let supply = self.total_supply();
+            let pooled = self.total_pooled();
+            if supply == 0 || pooled == 0 {
+                return value;
+            }
+            multiply_by_rational_with_rounding(value, supply, pooled, Rounding::Down)
+                .unwrap_or(Balance::MAX)
+        }
+
+        #[ink(message)]
+        pub fn unbond_requests(&self, account: AccountId) -> Vec<UnbondRequest> {
+            self.unbond_requests.get(account).unwrap_or_default()
+        }
+
+        /// Era the requests of `era` can be withdrawn from, once they are unbonded
+        #[ink(message)]
+        pub fn unlock_era(&self, era: u32) -> Option<u32> {
+            self.unlock_eras.get(era)
+        }
+
+        /// Stakes the transferred value on the dApp and mints shares to the caller
+        #[ink(message, payable)]
+        pub fn bond_and_stake(&mut self) -> Result<Balance, LiquidStakingError> {
+            let caller = self.env().caller();
+            let value = self.env().transferred_value();
+            if value == 0 {
+                return Err(LiquidStakingError::ZeroAmount);
+            }
+            // shares are priced before the stake changes the pool
+            let shares = self.value_to_shares(value);
+            if shares == 0 {
+                return Err(LiquidStakingError::AmountTooSmall);
+            }
+            DappsStaking::bond_and_stake(self.dapp, value)?;
+            self._mint_to(caller, shares)?;
+            self.env().emit_event(Staked {
+                staker: caller,
+                value,
+                shares,
+            });
+            Ok(shares)
+        }
+
+        /// Claims the oldest unclaimed era of staker rewards for the pool.
+        /// Anyone can call it; rewards are restaked and raise the share value.
+        #[ink(message)]
+        pub fn claim_staker(&mut self) -> Result<Balance, LiquidStakingError> {
+            let pooled = self.total_pooled();
+            DappsStaking::claim_staker(self.dapp)?;
+            let reward = self.total_pooled().saturating_sub(pooled);
+            self.env().emit_event(RewardsClaimed { reward });
+            Ok(reward)
+        }
+
+        /// Burns `shares` of the caller and queues their value for unbonding with
+        /// the other requests of the era. It can be withdrawn with `withdraw_unbonded`
+        /// once the batch is unbonded and the unbonding period passed.
+        #[ink(message)]
+        pub fn request_unbond(&mut self, shares: Balance) -> Result<Balance, LiquidStakingError> {
+            let caller = self.env().caller();
+            if shares == 0 {
+                return Err(LiquidStakingError::ZeroAmount);
+            }
+            self.unbond_queued()?;
+            let value = self.shares_to_value(shares);
+            if value == 0 {
+                return Err(LiquidStakingError::AmountTooSmall);
+            }
+            // the pallet would unstake the rest too, which belongs to other holders
+            let rest = self.total_pooled().saturating_sub(value);
+            if rest > 0 && rest < self.minimum_stake {
+                return Err(LiquidStakingError::BelowMinimumStake);
+            }
+            let era = DappsStaking::read_current_era();
+            let mut requests = self.unbond_requests.get(caller).unwrap_or_default();
+            match requests.last_mut() {
+                Some(last) if last.era == era => {
+                    last.value = last
+                        .value
+                        .checked_add(value)
+                        .ok_or(LiquidStakingError::Overflow)?;
+                }
+                _ => {
+                    if requests.len() as u32 >= MAX_UNBOND_REQUESTS {
+                        return Err(LiquidStakingError::TooManyUnbondRequests);
+                    }
+                    requests.push(UnbondRequest { value, era });
+                }
+            }
+
+            self._burn_from(caller, shares)?;
+            self.unbond_requests.insert(caller, &requests);
+            self.queued += value;
+            self.queued_era = era;
+            self.total_unbonding = self
+                .total_unbonding
+                .checked_add(value)
+                .ok_or(LiquidStakingError::Overflow)?;
+
+            self.env().emit_event(UnbondRequested {
+                staker: caller,
+                shares,
+                value,
+                era,
+            });
+            Ok(value)
+        }
+
+        /// Unbonds the value requested in a past era, in one pallet call per era.
+        /// Anyone can call it, the first `request_unbond` of an era does.
+        #[ink(message)]
+        pub fn unbond_queued(&mut self) -> Result<Balance, LiquidStakingError> {
+            let current_era = DappsStaking::read_current_era();
+            let value = self.queued;
+            if value == 0 || self.queued_era >= current_era {
+                return Ok(0);
+            }
+            // matured chunks are withdrawn to free their slots in the pallet
+            match DappsStaking::withdraw_unbonded() {
+                Ok(()) | Err(DSError::NothingToWithdraw) => {}
+                Err(error) => return Err(error.into()),
+            }
+            DappsStaking::unbond_and_unstake(self.dapp, value)?;
+
+            let unlock_era = current_era + DappsStaking::read_unbonding_period();
+            self.unlock_eras.insert(self.queued_era, &unlock_era);
+            self.queued = 0;
+            self.env().emit_event(Unbonded {
+                era: self.queued_era,
+                value,
+                unlock_era,
+            });
+            Ok(value)
+        }
+
+        /// Pays out every matured unbond request of the caller
+        #[ink(message)]
+        pub fn withdraw_unbonded(&mut self) -> Result<Balance, LiquidStakingError> {
+            let caller = self.env().caller();
+            let current_era = DappsStaking::read_current_era();
+            let requests = self.unbond_requests.get(caller).unwrap_or_default();
+            let (matured, pending): (Vec<_>, Vec<_>) = requests.into_iter().partition(|request| {
+                let unlock_era = self.unlock_eras.get(request.era);
+                matches!(unlock_era, Some(era) if era <= current_era)
+            });
+            let value: Balance = matured.iter().map(|request| request.value).sum();
+            if value == 0 {
+                return Err(LiquidStakingError::NothingToWithdraw);
+            }
+
+            // chunks of every staker are withdrawn at once, so an earlier caller
+            // may already have moved this value to the contract balance
+            match DappsStaking::withdraw_unbonded() {
+                Ok(()) | Err(DSError::NothingToWithdraw) => {}
+                Err(error) => return Err(error.into()),
+            }
+
+            if pending.is_empty() {
+                self.unbond_requests.remove(caller);
+            } else {
+                self.unbond_requests.insert(caller, &pending);
+            }
+            self.total_unbonding -= value;
+            self.env()
+                .transfer(caller, value)
+                .map_err(|_| LiquidStakingError::TransferFailed)?;
+            self.env().emit_event(Withdrawn {
+                staker: caller,
+                value,
+            });
+            Ok(value)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use dapps_staking_extension::mock::DappsStakingMock;
+        use ink::env::{test, DefaultEnvironment};
+
+        const ONE: Balance = 1_000_000_000_000_000_000;
+
+        struct Setup {
+            contract: LiquidStaking,
+            mock: DappsStakingMock,
+            address: AccountId,
+        }
+
+        fn setup() -> Setup {
+            let address = AccountId::from([0xC0; 32]);
+            let dapp = AccountId::from([0xDA; 32]);
+            test::set_callee::<DefaultEnvironment>(address);
+            let mock = DappsStakingMock::register(address);
+            mock.register_dapp(dapp);
+            mock.set_unbonding_period(2);
+            Setup {
+                contract: LiquidStaking::new(dapp, 0, Some(String::from("Staked ASTR")), None),
+                mock,
+                address,
+            }
+        }
+
+        fn stake(
+            contract: &mut LiquidStaking,
+            staker: AccountId,
+            value: Balance,
+        ) -> Result<Balance, LiquidStakingError> {
+            test::set_caller::<DefaultEnvironment>(staker);
+            test::set_value_transferred::<DefaultEnvironment>(value);
+            let result = contract.bond_and_stake();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+            result
+        }
+
+        #[ink::test]
+        fn first_stake_mints_shares_one_to_one() {
+            let Setup {
+                mut contract,
+                mock,
+                address,
+            } = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+
+            assert_eq!(stake(&mut contract, accounts.bob, 100 * ONE), Ok(100 * ONE));
+            assert_eq!(contract.balance_of(accounts.bob), 100 * ONE);
+            assert_eq!(contract.total_pooled(), 100 * ONE);
+            assert_eq!(mock.staked(address, contract.dapp()), 100 * ONE);
+        }
+
+        #[ink::test]
+        fn zero_stake_fails() {
+            let Setup { mut contract, .. } = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+
+            assert_eq!(
+                stake(&mut contract, accounts.bob, 0),
+                Err(LiquidStakingError::ZeroAmount)
+            );
+        }
+
+        #[ink::test]
+        fn stake_on_unregistered_dapp_fails_without_minting() {
+            let address = AccountId::from([0xC0; 32]);
+            test::set_callee::<DefaultEnvironment>(address);
+            DappsStakingMock::register(address);
+            let mut contract = LiquidStaking::new(AccountId::from([0xDA; 32]), 0, None, None);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+
+            assert_eq!(
+                stake(&mut contract, accounts.bob, ONE),
+                Err(LiquidStakingError::DappsStaking(
+                    DSError::NotOperatedContract
+                ))
+            );
+            assert_eq!(contract.total_supply(), 0);
+        }
+
+        #[ink::test]
+        fn claimed_rewards_raise_exchange_rate() {
+            let Setup {
+                mut contract, mock, ..
+            } = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            stake(&mut contract, accounts.bob, 100 * ONE).unwrap();
+
+            mock.set_era_rewards(0, 10 * ONE, 0);
+            mock.advance_era();
+            assert_eq!(contract.claim_staker(), Ok(10 * ONE));
+            assert_eq!(contract.total_pooled(), 110 * ONE);
+            assert_eq!(contract.shares_to_value(100 * ONE), 110 * ONE);
+
+            // charlie gets fewer shares for the same value
+            assert_eq!(
+                stake(&mut contract, accounts.charlie, 110 * ONE),
+                Ok(100 * ONE)
+            );
+            assert_eq!(contract.shares_to_value(100 * ONE), 110 * ONE);
+        }
+
+        #[ink::test]
+        fn claim_fails_when_no_era_ended() {
+            let Setup { mut contract, .. } = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            stake(&mut contract, accounts.bob, 100 * ONE).unwrap();
+
+            assert_eq!(
+                contract.claim_staker(),
+                Err(LiquidStakingError::DappsStaking(DSError::EraOutOfBounds))
+            );
+        }
+
+        #[ink::test]
+        fn unbond_request_is_queued_until_period_passed() {
+            let Setup {
+                mut contract,
+                mock,
+                address,
+            } = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            stake(&mut contract, accounts.bob, 100 * ONE).unwrap();
+
+            assert_eq!(contract.request_unbond(40 * ONE), Ok(40 * ONE));
+            assert_eq!(contract.balance_of(accounts.bob), 60 * ONE);
+            assert_eq!(contract.total_pooled(), 60 * ONE);
+            assert_eq!(contract.total_unbonding(), 40 * ONE);
+            assert_eq!(
+                contract.unbond_requests(accounts.bob),
+                vec![UnbondRequest {
+                    value: 40 * ONE,
+                    era: 0
+                }]
+            );
+            // still staked until the era ends
+            assert_eq!(contract.unbond_queued(), Ok(0));
+            assert_eq!(mock.staked(address, contract.dapp()), 100 * ONE);
+
+            mock.advance_era();
+            assert_eq!(contract.unbond_queued(), Ok(40 * ONE));
+            assert_eq!(mock.staked(address, contract.dapp()), 60 * ONE);
+            assert_eq!(contract.unlock_era(0), Some(3));
+            assert_eq!(contract.total_pooled(), 60 * ONE);
+            assert_eq!(
+                contract.withdraw_unbonded(),
+                Err(LiquidStakingError::NothingToWithdraw)
+            );
+        }
+
+        #[ink::test]
+        fn requests_of_an_era_are_unbonded_together() {
+            let Setup {
+                mut contract,
+                mock,
+                address,
+            } = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            stake(&mut contract, accounts.bob, 100 * ONE).unwrap();
+            stake(&mut contract, accounts.charlie, 100 * ONE).unwrap();
+
+            contract.request_unbond(30 * ONE).unwrap();
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            contract.request_unbond(10 * ONE).unwrap();
+            contract.request_unbond(10 * ONE).unwrap();
+            assert_eq!(
+                contract.unbond_requests(accounts.bob),
+                vec![UnbondRequest {
+                    value: 20 * ONE,
+                    era: 0
+                }]
+            );
+
+            // the first request of the next era unbonds the batch first
+            mock.advance_era();
+            contract.request_unbond(ONE).unwrap();
+            assert_eq!(mock.staked(address, contract.dapp()), 150 * ONE);
+            assert_eq!(contract.total_pooled(), 149 * ONE);
+            assert_eq!(contract.total_unbonding(), 51 * ONE);
+            assert_eq!(contract.unlock_era(0), Some(3));
+            assert_eq!(contract.unlock_era(1), None);
+        }
+
+        #[ink::test]
+        fn unbond_keeps_minimum_stake() {
+            let Setup { mock, .. } = setup();
+            let mut contract =
+                LiquidStaking::new(AccountId::from([0xDA; 32]), 10 * ONE, None, None);
+            mock.set_minimum_stake(10 * ONE);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            stake(&mut contract, accounts.bob, 100 * ONE).unwrap();
+
+            assert_eq!(
+                contract.request_unbond(95 * ONE),
+                Err(LiquidStakingError::BelowMinimumStake)
+            );
+            assert_eq!(contract.request_unbond(90 * ONE), Ok(90 * ONE));
+            // the last shares take the whole stake
+            assert_eq!(contract.request_unbond(10 * ONE), Ok(10 * ONE));
+            mock.advance_era();
+            assert_eq!(contract.unbond_queued(), Ok(100 * ONE));
+            assert_eq!(contract.total_pooled(), 0);
+        }
+
+        #[ink::test]
+        fn unbond_more_than_balance_fails() {
+            let Setup { mut contract, .. } = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            stake(&mut contract, accounts.bob, 100 * ONE).unwrap();
+
+            assert_eq!(
+                contract.request_unbond(101 * ONE),
+                Err(LiquidStakingError::PSP22(PSP22Error::InsufficientBalance))
+            );
+        }
+
+        #[ink::test]
+        fn withdraw_pays_out_matured_requests() {
+            let Setup {
+                mut contract,
+                mock,
+                address,
+            } = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            stake(&mut contract, accounts.bob, 100 * ONE).unwrap();
+            mock.set_era_rewards(0, 10 * ONE, 0);
+            mock.advance_era();
+            contract.claim_staker().unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.request_unbond(50 * ONE), Ok(55 * ONE));
+            mock.advance_era();
+            contract.unbond_queued().unwrap();
+            mock.advance_era();
+            mock.advance_era();
+
+            // the mock pallet does not move native funds, so fund the contract up front
+            test::set_account_balance::<DefaultEnvironment>(address, 55 * ONE);
+            let bob_balance =
+                test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap();
+            assert_eq!(contract.withdraw_unbonded(), Ok(55 * ONE));
+            assert_eq!(mock.free_balance(address), 55 * ONE);
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap(),
+                bob_balance + 55 * ONE
+            );
+            assert_eq!(contract.total_unbonding(), 0);
+            assert!(contract.unbond_requests(accounts.bob).is_empty());
+            assert_eq!(
+                contract.withdraw_unbonded(),
+                Err(LiquidStakingError::NothingToWithdraw)
+            );
+        }
+
+        #[ink::test]
+        fn unbond_requests_are_bounded() {
+            let Setup {
+                mut contract, mock, ..
+            } = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            stake(&mut contract, accounts.bob, 100 * ONE).unwrap();
+
+            for _ in 0..MAX_UNBOND_REQUESTS {
+                contract.request_unbond(ONE).unwrap();
+                mock.advance_era();
+            }
+            assert_eq!(
+                contract.request_unbond(ONE),
+                Err(LiquidStakingError::TooManyUnbondRequests)
+            );
+        }
+    }
+}
@@ -0,0 +1,311 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod merkle_airdrop {
+    use assets_extension::*;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+
+    #[ink(event)]
+    pub struct Claimed {
+        #[ink(topic)]
+        account: AccountId,
+        index: u32,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Swept {
+        #[ink(topic)]
+        to: AccountId,
+        amount: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum AirdropError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        NotOwner,
+        AlreadyClaimed,
+        /// The proof does not lead to the Merkle root
+        InvalidProof,
+        /// Claims are closed after the deadline
+        ClaimPeriodEnded,
+        /// Unclaimed funds can only be swept after the deadline
+        ClaimPeriodNotEnded,
+        NothingToSweep,
+    }
+
+    impl From<AssetsError> for AirdropError {
+        fn from(error: AssetsError) -> Self {
+            AirdropError::Assets(error)
+        }
+    }
+
+    /// How claimed amounts are paid
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Distribution {
+        /// Minted on claim, the contract must be admin of the asset
+        Mint,
+        /// Transferred from the balance of the contract, funded beforehand
+        Transfer,
+    }
+
+    /// Airdrops a pallet asset to the recipients of a Merkle tree, see
+    /// [`merkle_tree`] for how to build it.
+    #[ink(storage)]
+    pub struct MerkleAirdrop {
+        owner: AccountId,
+        asset_id: u128,
+        merkle_root: [u8; 32],
+        distribution: Distribution,
+        /// Last block at which claims are accepted
+        deadline: BlockNumber,
+        /// Claimed leaves, 128 per word
+        claimed: Mapping<u32, u128>,
+    }
+
+    impl MerkleAirdrop {
+        #[ink(constructor)]
+        pub fn new(
+            asset_id: u128,
+            merkle_root: [u8; 32],
+            distribution: Distribution,
+            deadline: BlockNumber,
+        ) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                asset_id,
+                merkle_root,
+                distribution,
+                deadline,
+                claimed: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn asset_id(&self) -> u128 {
+            self.asset_id
+        }
+
+        #[ink(message)]
+        pub fn merkle_root(&self) -> [u8; 32] {
+            self.merkle_root
+        }
+
+        #[ink(message)]
+        pub fn distribution(&self) -> Distribution {
+            self.distribution
+        }
+
+        #[ink(message)]
+        pub fn deadline(&self) -> BlockNumber {
+            self.deadline
+        }
+
+        #[ink(message)]
+        pub fn is_claimed(&self, index: u32) -> bool {
+            let (word, bit) = Self::bitmap_position(index);
+            self.claimed.get(word).unwrap_or_default() & bit != 0
+        }
+
+        /// Pays `amount` to `account`, which must be leaf `index` of the tree.
+        /// Anyone can claim on behalf of a recipient.
+        #[ink(message)]
+        pub fn claim(
+            &mut self,
+            index: u32,
+            account: AccountId,
+            amount: Balance,
+            proof: Vec<[u8; 32]>,
+        ) -> Result<(), AirdropError> {
+            if self.env().block_number() > self.deadline {
+                return Err(AirdropError::ClaimPeriodEnded);
+            }
+            if self.is_claimed(index) {
+                return Err(AirdropError::AlreadyClaimed);
+            }
+            let leaf = merkle_tree::leaf_hash(index, &account, amount);
+            if !merkle_tree::verify(&self.merkle_root, leaf, &proof) {
+                return Err(AirdropError::InvalidProof);
+            }
+            match self.distribution {
+                Distribution::Mint => {
+                    AssetsExtension::mint(Origin::Address, self.asset_id, account, amount)?
+                }
+                Distribution::Transfer => {
+                    AssetsExtension::transfer(Origin::Address, self.asset_id, account, amount)?
+                }
+            }
+            let (word, bit) = Self::bitmap_position(index);
+            let claimed = self.claimed.get(word).unwrap_or_default();
+            self.claimed.insert(word, &(claimed | bit));
+            self.env().emit_event(Claimed {
+                account,
+                index,
+                amount,
+            });
+            Ok(())
+        }
+
+        /// Sends the unclaimed balance of the contract to `to` once the deadline
+        /// has passed. With `Distribution::Mint` unclaimed amounts are simply
+        /// never minted, so only what was sent to the contract can be swept.
+        #[ink(message)]
+        pub fn sweep(&mut self, to: AccountId) -> Result<Balance, AirdropError> {
+            self.ensure_owner()?;
+            if self.env().block_number() <= self.deadline {
+                return Err(AirdropError::ClaimPeriodNotEnded);
+            }
+            let amount = AssetsExtension::balance_of(self.asset_id, self.env().account_id());
+            if amount == 0 {
+                return Err(AirdropError::NothingToSweep);
+            }
+            AssetsExtension::transfer(Origin::Address, self.asset_id, to, amount)?;
+            self.env().emit_event(Swept { to, amount });
+            Ok(amount)
+        }
+
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), AirdropError> {
+            self.ensure_owner()?;
+            self.owner = new_owner;
+            Ok(())
+        }
+
+        fn bitmap_position(index: u32) -> (u32, u128) {
+            (index / 128, 1 << (index % 128))
+        }
+
+        fn ensure_owner(&self) -> Result<(), AirdropError> {
+            if self.env().caller() != self.owner {
+                return Err(AirdropError::NotOwner);
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use assets_extension::mock::AssetsMock;
+        use ink::env::{test, DefaultEnvironment};
+        use merkle_tree::{MerkleTree, Recipient};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const ASSET: u128 = 1;
+
+        struct Setup {
+            airdrop: MerkleAirdrop,
+            mock: AssetsMock,
+            tree: MerkleTree,
+            recipients: Vec<Recipient>,
+        }
+
+        fn setup(distribution: Distribution) -> Setup {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mock = AssetsMock::register(AccountId::from(CONTRACT));
+            let recipients: Vec<_> = [(accounts.bob, 100), (accounts.charlie, 200)]
+                .into_iter()
+                .chain((0..3).map(|i| (AccountId::from([0x10 + i; 32]), 1)))
+                .map(|(account, amount)| Recipient { account, amount })
+                .collect();
+            let tree = MerkleTree::new(&recipients);
+            match distribution {
+                Distribution::Mint => mock.create(ASSET, AccountId::from(CONTRACT), 1),
+                Distribution::Transfer => {
+                    mock.create(ASSET, accounts.alice, 1);
+                    mock.mint(ASSET, AccountId::from(CONTRACT), 303);
+                }
+            }
+            let airdrop = MerkleAirdrop::new(ASSET, tree.root(), distribution, 10);
+            Setup {
+                airdrop,
+                mock,
+                tree,
+                recipients,
+            }
+        }
+
+        impl Setup {
+            fn claim(&mut self, index: u32) -> Result<(), AirdropError> {
+                let recipient = self.recipients[index as usize];
+                let proof = self.tree.proof(index).unwrap();
+                self.airdrop
+                    .claim(index, recipient.account, recipient.amount, proof)
+            }
+        }
+
+        #[ink::test]
+        fn claims_once_with_valid_proof() {
+            let mut setup = setup(Distribution::Transfer);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+
+            assert_eq!(setup.claim(1), Ok(()));
+            assert_eq!(setup.mock.balance_of(ASSET, accounts.charlie), 200);
+            assert!(setup.airdrop.is_claimed(1));
+            assert!(!setup.airdrop.is_claimed(0));
+            assert_eq!(setup.claim(1), Err(AirdropError::AlreadyClaimed));
+
+            let proof = setup.tree.proof(0).unwrap();
+            assert_eq!(
+                setup.airdrop.claim(0, accounts.bob, 101, proof.clone()),
+                Err(AirdropError::InvalidProof)
+            );
+            assert_eq!(
+                setup.airdrop.claim(2, accounts.bob, 100, proof),
+                Err(AirdropError::InvalidProof)
+            );
+        }
+
+        #[ink::test]
+        fn mints_on_claim() {
+            let mut setup = setup(Distribution::Mint);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+
+            for index in 0..setup.recipients.len() as u32 {
+                assert_eq!(setup.claim(index), Ok(()));
+            }
+            assert_eq!(setup.mock.balance_of(ASSET, accounts.bob), 100);
+            assert_eq!(setup.mock.asset(ASSET).unwrap().supply, 303);
+        }
+
+        #[ink::test]
+        fn sweeps_unclaimed_after_deadline() {
+            let mut setup = setup(Distribution::Transfer);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            assert_eq!(setup.claim(0), Ok(()));
+            assert_eq!(
+                setup.airdrop.sweep(accounts.alice),
+                Err(AirdropError::ClaimPeriodNotEnded)
+            );
+
+            for _ in 0..=10 {
+                test::advance_block::<DefaultEnvironment>();
+            }
+            assert_eq!(setup.claim(1), Err(AirdropError::ClaimPeriodEnded));
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                setup.airdrop.sweep(accounts.bob),
+                Err(AirdropError::NotOwner)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(setup.airdrop.sweep(accounts.alice), Ok(203));
+            assert_eq!(setup.mock.balance_of(ASSET, accounts.alice), 203);
+            assert_eq!(
+                setup.airdrop.sweep(accounts.alice),
+                Err(AirdropError::NothingToSweep)
+            );
+        }
+    }
+}
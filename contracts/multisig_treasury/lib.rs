@@ -0,0 +1,529 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod multisig_treasury {
+    use assets_extension::{AssetsError, AssetsExtension};
+    use ink::env::call::Selector;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use scheduler_extension::{
+        ContractCallInput, SchedulerError, SchedulerExtension, CALL_GAS_LIMIT, CALL_MAX_WEIGHT,
+    };
+
+    /// Maximum number of owners
+    pub const MAX_OWNERS: u32 = 16;
+
+    #[ink(event)]
+    pub struct Proposed {
+        #[ink(topic)]
+        proposal_id: u64,
+        #[ink(topic)]
+        proposer: AccountId,
+        operation: Operation,
+    }
+
+    #[ink(event)]
+    pub struct Approved {
+        #[ink(topic)]
+        proposal_id: u64,
+        #[ink(topic)]
+        owner: AccountId,
+        approvals: u32,
+    }
+
+    #[ink(event)]
+    pub struct Revoked {
+        #[ink(topic)]
+        proposal_id: u64,
+        #[ink(topic)]
+        owner: AccountId,
+        approvals: u32,
+    }
+
+    /// Audit record of an executed proposal
+    #[ink(event)]
+    pub struct Executed {
+        #[ink(topic)]
+        proposal_id: u64,
+        operation: Operation,
+        /// Owners who approved the proposal
+        approvers: Vec<AccountId>,
+        executed_by: AccountId,
+        block: BlockNumber,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum MultisigError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        /// Error from the scheduler pallet
+        Scheduler(SchedulerError),
+        NotOwner,
+        /// Owners are empty, more than `MAX_OWNERS` or not unique
+        InvalidOwners,
+        /// Threshold is zero or above the number of owners
+        InvalidThreshold,
+        ProposalNotFound,
+        AlreadyApproved,
+        NotApproved,
+        AlreadyExecuted,
+        NotEnoughApprovals,
+        /// The timelock of the proposal has not passed yet
+        Timelocked,
+    }
+
+    impl From<AssetsError> for MultisigError {
+        fn from(error: AssetsError) -> Self {
+            MultisigError::Assets(error)
+        }
+    }
+
+    impl From<SchedulerError> for MultisigError {
+        fn from(error: SchedulerError) -> Self {
+            MultisigError::Scheduler(error)
+        }
+    }
+
+    /// Pallet-assets call dispatched with the treasury as origin
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Operation {
+        Transfer {
+            asset_id: u128,
+            to: AccountId,
+            amount: Balance,
+        },
+        Mint {
+            asset_id: u128,
+            to: AccountId,
+            amount: Balance,
+        },
+        Burn {
+            asset_id: u128,
+            who: AccountId,
+            amount: Balance,
+        },
+        SetMetadata {
+            asset_id: u128,
+            name: Vec<u8>,
+            symbol: Vec<u8>,
+            decimals: u8,
+        },
+        TransferOwnership {
+            asset_id: u128,
+            owner: AccountId,
+        },
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Proposal {
+        pub operation: Operation,
+        pub proposer: AccountId,
+        pub approvals: u32,
+        /// Block from which the proposal can be executed, set once it has
+        /// enough approvals
+        pub executable_at: Option<BlockNumber>,
+        pub executed: bool,
+    }
+
+    /// M-of-N multisig owning pallet assets.
+    ///
+    /// A proposal can be executed once `threshold` owners approved it and the
+    /// timelock has passed. With a timelock, the execution is scheduled when the
+    /// threshold is reached, paid from the contract balance.
+    #[ink(storage)]
+    pub struct MultisigTreasury {
+        owners: Vec<AccountId>,
+        threshold: u32,
+        /// Blocks between reaching the threshold and execution, 0 for none
+        timelock: BlockNumber,
+        proposals: Mapping<u64, Proposal>,
+        approvals: Mapping<(u64, AccountId), ()>,
+        next_proposal_id: u64,
+    }
+
+    impl MultisigTreasury {
+        #[ink(constructor)]
+        pub fn new(
+            owners: Vec<AccountId>,
+            threshold: u32,
+            timelock: BlockNumber,
+        ) -> Result<Self, MultisigError> {
+            if owners.is_empty() || owners.len() as u32 > MAX_OWNERS {
+                return Err(MultisigError::InvalidOwners);
+            }
+            if owners
+                .iter()
+                .enumerate()
+                .any(|(index, owner)| owners[..index].contains(owner))
+            {
+                return Err(MultisigError::InvalidOwners);
+            }
+            if threshold == 0 || threshold > owners.len() as u32 {
+                return Err(MultisigError::InvalidThreshold);
+            }
+            Ok(Self {
+                owners,
+                threshold,
+                timelock,
+                proposals: Mapping::default(),
+                approvals: Mapping::default(),
+                next_proposal_id: 0,
+            })
+        }
+
+        #[ink(message)]
+        pub fn owners(&self) -> Vec<AccountId> {
+            self.owners.clone()
+        }
+
+        #[ink(message)]
+        pub fn threshold(&self) -> u32 {
+            self.threshold
+        }
+
+        #[ink(message)]
+        pub fn timelock(&self) -> BlockNumber {
+            self.timelock
+        }
+
+        #[ink(message)]
+        pub fn proposal(&self, proposal_id: u64) -> Option<Proposal> {
+            self.proposals.get(proposal_id)
+        }
+
+        #[ink(message)]
+        pub fn has_approved(&self, proposal_id: u64, owner: AccountId) -> bool {
+            self.approvals.contains((proposal_id, owner))
+        }
+
+        /// Proposes `operation`, approved by the caller
+        #[ink(message)]
+        pub fn propose(&mut self, operation: Operation) -> Result<u64, MultisigError> {
+            let proposer = self.ensure_owner()?;
+            let proposal_id = self.next_proposal_id;
+            self.next_proposal_id += 1;
+            self.proposals.insert(
+                proposal_id,
+                &Proposal {
+                    operation: operation.clone(),
+                    proposer,
+                    approvals: 0,
+                    executable_at: None,
+                    executed: false,
+                },
+            );
+            self.env().emit_event(Proposed {
+                proposal_id,
+                proposer,
+                operation,
+            });
+            self.approve(proposal_id)?;
+            Ok(proposal_id)
+        }
+
+        #[ink(message)]
+        pub fn approve(&mut self, proposal_id: u64) -> Result<(), MultisigError> {
+            let owner = self.ensure_owner()?;
+            let mut proposal = self.pending_proposal(proposal_id)?;
+            if self.has_approved(proposal_id, owner) {
+                return Err(MultisigError::AlreadyApproved);
+            }
+            self.approvals.insert((proposal_id, owner), &());
+            proposal.approvals += 1;
+            if proposal.approvals == self.threshold {
+                let executable_at = self.env().block_number() + self.timelock;
+                if self.timelock > 0 {
+                    SchedulerExtension::schedule(
+                        scheduler_extension::Origin::Address,
+                        executable_at,
+                        None,
+                        0,
+                        self.execute_call(proposal_id),
+                    )?;
+                }
+                proposal.executable_at = Some(executable_at);
+            }
+            self.proposals.insert(proposal_id, &proposal);
+            self.env().emit_event(Approved {
+                proposal_id,
+                owner,
+                approvals: proposal.approvals,
+            });
+            Ok(())
+        }
+
+        /// Withdraws the approval of the caller. Dropping below the threshold
+        /// resets the timelock. The scheduler has no way to cancel the already
+        /// scheduled `execute`: it still runs, paid by the contract, and fails
+        /// with `NotEnoughApprovals`, or with `Timelocked` if the proposal was
+        /// approved again in the meantime.
+        #[ink(message)]
+        pub fn revoke(&mut self, proposal_id: u64) -> Result<(), MultisigError> {
+            let owner = self.ensure_owner()?;
+            let mut proposal = self.pending_proposal(proposal_id)?;
+            if self.approvals.take((proposal_id, owner)).is_none() {
+                return Err(MultisigError::NotApproved);
+            }
+            proposal.approvals -= 1;
+            if proposal.approvals < self.threshold {
+                proposal.executable_at = None;
+            }
+            self.proposals.insert(proposal_id, &proposal);
+            self.env().emit_event(Revoked {
+                proposal_id,
+                owner,
+                approvals: proposal.approvals,
+            });
+            Ok(())
+        }
+
+        /// Dispatches an approved proposal once its timelock has passed.
+        /// Called by the scheduler with a timelock, but anyone can call it.
+        #[ink(message)]
+        pub fn execute(&mut self, proposal_id: u64) -> Result<(), MultisigError> {
+            let mut proposal = self.pending_proposal(proposal_id)?;
+            let executable_at = proposal
+                .executable_at
+                .ok_or(MultisigError::NotEnoughApprovals)?;
+            let block = self.env().block_number();
+            if block < executable_at {
+                return Err(MultisigError::Timelocked);
+            }
+            self.dispatch(&proposal.operation)?;
+            proposal.executed = true;
+            self.proposals.insert(proposal_id, &proposal);
+            self.env().emit_event(Executed {
+                proposal_id,
+                approvers: self
+                    .owners
+                    .iter()
+                    .filter(|owner| self.has_approved(proposal_id, **owner))
+                    .copied()
+                    .collect(),
+                operation: proposal.operation,
+                executed_by: self.env().caller(),
+                block,
+            });
+            Ok(())
+        }
+
+        fn dispatch(&self, operation: &Operation) -> Result<(), AssetsError> {
+            use assets_extension::Origin::Address;
+            match operation.clone() {
+                Operation::Transfer {
+                    asset_id,
+                    to,
+                    amount,
+                } => AssetsExtension::transfer(Address, asset_id, to, amount),
+                Operation::Mint {
+                    asset_id,
+                    to,
+                    amount,
+                } => AssetsExtension::mint(Address, asset_id, to, amount),
+                Operation::Burn {
+                    asset_id,
+                    who,
+                    amount,
+                } => AssetsExtension::burn(Address, asset_id, who, amount),
+                Operation::SetMetadata {
+                    asset_id,
+                    name,
+                    symbol,
+                    decimals,
+                } => AssetsExtension::set_metadata(Address, asset_id, name, symbol, decimals),
+                Operation::TransferOwnership { asset_id, owner } => {
+                    AssetsExtension::transfer_ownership(Address, asset_id, owner)
+                }
+            }
+        }
+
+        fn pending_proposal(&self, proposal_id: u64) -> Result<Proposal, MultisigError> {
+            let proposal = self
+                .proposals
+                .get(proposal_id)
+                .ok_or(MultisigError::ProposalNotFound)?;
+            if proposal.executed {
+                return Err(MultisigError::AlreadyExecuted);
+            }
+            Ok(proposal)
+        }
+
+        fn execute_call(&self, proposal_id: u64) -> ContractCallInput {
+            let mut data = Selector::new(ink::selector_bytes!("execute"))
+                .to_bytes()
+                .to_vec();
+            scale::Encode::encode_to(&proposal_id, &mut data);
+            ContractCallInput {
+                dest: self.env().account_id(),
+                data,
+                gas_limit: CALL_GAS_LIMIT,
+                storage_deposit_limit: None,
+                value: 0,
+                max_weight: CALL_MAX_WEIGHT,
+            }
+        }
+
+        fn ensure_owner(&self) -> Result<AccountId, MultisigError> {
+            let caller = self.env().caller();
+            if !self.owners.contains(&caller) {
+                return Err(MultisigError::NotOwner);
+            }
+            Ok(caller)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink::env::{test, DefaultEnvironment};
+        use scheduler_extension::mock::Mocks;
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const ASSET: u128 = 1;
+
+        fn setup(
+            timelock: BlockNumber,
+        ) -> (
+            MultisigTreasury,
+            Mocks,
+            test::DefaultAccounts<DefaultEnvironment>,
+        ) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mocks = Mocks::register(AccountId::from(CONTRACT));
+            mocks.assets.create(ASSET, AccountId::from(CONTRACT), 1);
+            mocks.set_caller(accounts.alice);
+            let owners = vec![accounts.alice, accounts.bob, accounts.charlie];
+            let treasury = MultisigTreasury::new(owners, 2, timelock).unwrap();
+            (treasury, mocks, accounts)
+        }
+
+        fn mint(to: AccountId, amount: Balance) -> Operation {
+            Operation::Mint {
+                asset_id: ASSET,
+                to,
+                amount,
+            }
+        }
+
+        #[ink::test]
+        fn executes_with_threshold_approvals() {
+            let (mut treasury, mocks, accounts) = setup(0);
+            let proposal_id = treasury.propose(mint(accounts.django, 100)).unwrap();
+            assert_eq!(
+                treasury.execute(proposal_id),
+                Err(MultisigError::NotEnoughApprovals)
+            );
+            assert_eq!(
+                treasury.approve(proposal_id),
+                Err(MultisigError::AlreadyApproved)
+            );
+
+            mocks.set_caller(accounts.django);
+            assert_eq!(treasury.approve(proposal_id), Err(MultisigError::NotOwner));
+            mocks.set_caller(accounts.bob);
+            treasury.approve(proposal_id).unwrap();
+            assert_eq!(treasury.execute(proposal_id), Ok(()));
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.django), 100);
+            assert_eq!(
+                treasury.execute(proposal_id),
+                Err(MultisigError::AlreadyExecuted)
+            );
+
+            let proposal_id = treasury
+                .propose(Operation::TransferOwnership {
+                    asset_id: ASSET,
+                    owner: accounts.bob,
+                })
+                .unwrap();
+            mocks.set_caller(accounts.charlie);
+            treasury.approve(proposal_id).unwrap();
+            treasury.execute(proposal_id).unwrap();
+            assert_eq!(mocks.assets.asset(ASSET).unwrap().owner, accounts.bob);
+        }
+
+        #[ink::test]
+        fn scheduler_executes_after_timelock() {
+            let (mut treasury, mocks, accounts) = setup(10);
+            let proposal_id = treasury
+                .propose(Operation::SetMetadata {
+                    asset_id: ASSET,
+                    name: b"Treasury".to_vec(),
+                    symbol: b"TRS".to_vec(),
+                    decimals: 12,
+                })
+                .unwrap();
+            mocks.set_caller(accounts.bob);
+            treasury.approve(proposal_id).unwrap();
+            assert_eq!(
+                treasury.proposal(proposal_id).unwrap().executable_at,
+                Some(10)
+            );
+            assert_eq!(
+                treasury.execute(proposal_id),
+                Err(MultisigError::Timelocked)
+            );
+
+            let due = mocks.scheduler.run_to_block(10);
+            assert_eq!(due.len(), 1);
+            assert_eq!(due[0].1.origin, AccountId::from(CONTRACT));
+            assert_eq!(due[0].1.call, treasury.execute_call(proposal_id));
+            // dispatched by the scheduler
+            mocks.set_caller(AccountId::from(CONTRACT));
+            assert_eq!(treasury.execute(proposal_id), Ok(()));
+            assert_eq!(mocks.assets.asset(ASSET).unwrap().symbol, b"TRS");
+        }
+
+        #[ink::test]
+        fn revoked_proposal_fails_its_scheduled_execution() {
+            let (mut treasury, mocks, accounts) = setup(10);
+            let proposal_id = treasury.propose(mint(accounts.bob, 1)).unwrap();
+            mocks.set_caller(accounts.bob);
+            assert_eq!(
+                treasury.revoke(proposal_id),
+                Err(MultisigError::NotApproved)
+            );
+            treasury.approve(proposal_id).unwrap();
+            treasury.revoke(proposal_id).unwrap();
+            assert_eq!(treasury.proposal(proposal_id).unwrap().executable_at, None);
+
+            // the scheduled execution still runs and fails
+            let due = mocks.scheduler.run_to_block(10);
+            assert_eq!(due.len(), 1);
+            assert_eq!(due[0].1.call, treasury.execute_call(proposal_id));
+            mocks.set_caller(AccountId::from(CONTRACT));
+            assert_eq!(
+                treasury.execute(proposal_id),
+                Err(MultisigError::NotEnoughApprovals)
+            );
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn rejects_invalid_configuration() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let new = |owners, threshold| MultisigTreasury::new(owners, threshold, 0).err();
+            assert_eq!(new(vec![], 1), Some(MultisigError::InvalidOwners));
+            assert_eq!(
+                new(vec![accounts.alice, accounts.alice], 1),
+                Some(MultisigError::InvalidOwners)
+            );
+            assert_eq!(
+                new(vec![accounts.alice, accounts.bob], 3),
+                Some(MultisigError::InvalidThreshold)
+            );
+            assert_eq!(
+                new(vec![accounts.alice], 0),
+                Some(MultisigError::InvalidThreshold)
+            );
+        }
+    }
+}
@@ -0,0 +1,505 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod otc_escrow {
+    use assets_extension::{AssetsError, AssetsExtension};
+    use ink::env::call::Selector;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use scheduler_extension::{
+        ContractCallInput, SchedulerError, SchedulerExtension, CALL_GAS_LIMIT, CALL_MAX_WEIGHT,
+    };
+
+    /// Maximum number of offers returned by `offers`
+    pub const MAX_PAGE_SIZE: u32 = 50;
+
+    #[ink(event)]
+    pub struct OfferMade {
+        #[ink(topic)]
+        offer_id: u64,
+        #[ink(topic)]
+        maker: AccountId,
+        offer: Offer,
+    }
+
+    #[ink(event)]
+    pub struct OfferTaken {
+        #[ink(topic)]
+        offer_id: u64,
+        #[ink(topic)]
+        taker: AccountId,
+    }
+
+    /// Emitted when the locked token went back to the maker, cancelled by the
+    /// maker or expired
+    #[ink(event)]
+    pub struct OfferClosed {
+        #[ink(topic)]
+        offer_id: u64,
+        expired: bool,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum EscrowError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        /// Error from the scheduler pallet
+        Scheduler(SchedulerError),
+        ZeroAmount,
+        /// The transferred value does not match the native amount of the deal
+        WrongValue,
+        OfferNotFound,
+        NotMaker,
+        /// The offer is reserved to another taker
+        NotTaker,
+        /// The offer can no longer be taken
+        Expired,
+        /// The offer can only be expired from its expiry block
+        NotExpired,
+        /// Native transfer failed
+        TransferFailed,
+    }
+
+    impl From<AssetsError> for EscrowError {
+        fn from(error: AssetsError) -> Self {
+            EscrowError::Assets(error)
+        }
+    }
+
+    impl From<SchedulerError> for EscrowError {
+        fn from(error: SchedulerError) -> Self {
+            EscrowError::Scheduler(error)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Token {
+        Native,
+        Asset(u128),
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Offer {
+        pub maker: AccountId,
+        /// Locked by the maker, sent to the taker
+        pub give: (Token, Balance),
+        /// Paid by the taker to the maker
+        pub want: (Token, Balance),
+        /// Only account allowed to take the offer, anyone if `None`
+        pub taker: Option<AccountId>,
+        /// First block at which the offer can no longer be taken
+        pub expiry: BlockNumber,
+    }
+
+    /// Escrow of OTC deals between pallet assets and the native currency.
+    ///
+    /// The maker locks what it gives in the contract, the taker pays the maker
+    /// and receives it in the same call. Each offer schedules its own `expire`
+    /// call at its expiry block, paid from the contract balance, which gives the
+    /// locked token back to the maker if the offer is still open. The `expire`
+    /// of an offer taken or cancelled before still runs and fails with
+    /// `OfferNotFound`.
+    #[ink(storage)]
+    pub struct OtcEscrow {
+        offers: Mapping<u64, Offer>,
+        next_offer_id: u64,
+        /// Open offer ids, in no particular order
+        open_offers: Mapping<u32, u64>,
+        /// Position of each open offer in `open_offers`
+        open_indices: Mapping<u64, u32>,
+        open_count: u32,
+    }
+
+    impl OtcEscrow {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                offers: Mapping::default(),
+                next_offer_id: 0,
+                open_offers: Mapping::default(),
+                open_indices: Mapping::default(),
+                open_count: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn offer(&self, offer_id: u64) -> Option<Offer> {
+            self.offers.get(offer_id)
+        }
+
+        #[ink(message)]
+        pub fn open_count(&self) -> u32 {
+            self.open_count
+        }
+
+        /// Order book: up to `limit` open offers from position `start`, at most
+        /// `MAX_PAGE_SIZE`. Positions change as offers are closed.
+        #[ink(message)]
+        pub fn offers(&self, start: u32, limit: u32) -> Vec<(u64, Offer)> {
+            let end = start
+                .saturating_add(limit.min(MAX_PAGE_SIZE))
+                .min(self.open_count);
+            (start..end)
+                .filter_map(|index| self.open_offers.get(index))
+                .filter_map(|offer_id| Some((offer_id, self.offers.get(offer_id)?)))
+                .collect()
+        }
+
+        /// Locks `give` from the caller until the offer is taken, cancelled or
+        /// expired at block `expiry`. A native `give` is the transferred value,
+        /// for an asset the caller should approve contract address as spender
+        /// beforehand.
+        #[ink(message, payable)]
+        pub fn make_offer(
+            &mut self,
+            give: (Token, Balance),
+            want: (Token, Balance),
+            taker: Option<AccountId>,
+            expiry: BlockNumber,
+        ) -> Result<u64, EscrowError> {
+            if give.1 == 0 || want.1 == 0 {
+                return Err(EscrowError::ZeroAmount);
+            }
+            if expiry <= self.env().block_number() {
+                return Err(EscrowError::Expired);
+            }
+            let maker = self.env().caller();
+            self.receive(give, maker, self.env().account_id())?;
+
+            let offer_id = self.next_offer_id;
+            self.next_offer_id += 1;
+            SchedulerExtension::schedule(
+                scheduler_extension::Origin::Address,
+                expiry,
+                None,
+                0,
+                self.expire_call(offer_id),
+            )?;
+            let offer = Offer {
+                maker,
+                give,
+                want,
+                taker,
+                expiry,
+            };
+            self.offers.insert(offer_id, &offer);
+            self.open_offers.insert(self.open_count, &offer_id);
+            self.open_indices.insert(offer_id, &self.open_count);
+            self.open_count += 1;
+            self.env().emit_event(OfferMade {
+                offer_id,
+                maker,
+                offer,
+            });
+            Ok(offer_id)
+        }
+
+        /// Pays `want` to the maker and sends `give` to the caller. A native
+        /// `want` is the transferred value, for an asset the caller should
+        /// approve contract address as spender beforehand.
+        #[ink(message, payable)]
+        pub fn take_offer(&mut self, offer_id: u64) -> Result<(), EscrowError> {
+            let offer = self
+                .offers
+                .get(offer_id)
+                .ok_or(EscrowError::OfferNotFound)?;
+            let taker = self.env().caller();
+            if matches!(offer.taker, Some(allowed) if allowed != taker) {
+                return Err(EscrowError::NotTaker);
+            }
+            if self.env().block_number() >= offer.expiry {
+                return Err(EscrowError::Expired);
+            }
+            self.receive(offer.want, taker, offer.maker)?;
+            self.send(offer.give, taker)?;
+            self.close(offer_id);
+            self.env().emit_event(OfferTaken { offer_id, taker });
+            Ok(())
+        }
+
+        /// Gives the locked token back to the maker
+        #[ink(message)]
+        pub fn cancel_offer(&mut self, offer_id: u64) -> Result<(), EscrowError> {
+            let offer = self
+                .offers
+                .get(offer_id)
+                .ok_or(EscrowError::OfferNotFound)?;
+            if self.env().caller() != offer.maker {
+                return Err(EscrowError::NotMaker);
+            }
+            self.refund(offer_id, offer, false)
+        }
+
+        /// Gives the locked token of an expired offer back to the maker.
+        /// Called by the scheduler at the expiry block, but anyone can call it.
+        #[ink(message)]
+        pub fn expire(&mut self, offer_id: u64) -> Result<(), EscrowError> {
+            let offer = self
+                .offers
+                .get(offer_id)
+                .ok_or(EscrowError::OfferNotFound)?;
+            if self.env().block_number() < offer.expiry {
+                return Err(EscrowError::NotExpired);
+            }
+            self.refund(offer_id, offer, true)
+        }
+
+        fn refund(
+            &mut self,
+            offer_id: u64,
+            offer: Offer,
+            expired: bool,
+        ) -> Result<(), EscrowError> {
+            self.send(offer.give, offer.maker)?;
+            self.close(offer_id);
+            self.env().emit_event(OfferClosed { offer_id, expired });
+            Ok(())
+        }
+
+        /// Removes the offer from storage and from the order book
+        fn close(&mut self, offer_id: u64) {
+            self.offers.remove(offer_id);
+            let Some(index) = self.open_indices.take(offer_id) else {
+                return;
+            };
+            self.open_count -= 1;
+            if index != self.open_count {
+                let last = self.open_offers.get(self.open_count).unwrap_or_default();
+                self.open_offers.insert(index, &last);
+                self.open_indices.insert(last, &index);
+            }
+            self.open_offers.remove(self.open_count);
+        }
+
+        /// Moves `amount` of `token` from `from` to `to`, the native amount being
+        /// the value transferred by `from` to the contract
+        fn receive(
+            &self,
+            (token, amount): (Token, Balance),
+            from: AccountId,
+            to: AccountId,
+        ) -> Result<(), EscrowError> {
+            match token {
+                Token::Native => {
+                    if self.env().transferred_value() != amount {
+                        return Err(EscrowError::WrongValue);
+                    }
+                    if to != self.env().account_id() {
+                        self.transfer_native(to, amount)?;
+                    }
+                }
+                Token::Asset(asset_id) => {
+                    if self.env().transferred_value() != 0 {
+                        return Err(EscrowError::WrongValue);
+                    }
+                    AssetsExtension::transfer_approved(
+                        assets_extension::Origin::Address,
+                        asset_id,
+                        from,
+                        to,
+                        amount,
+                    )?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Sends `amount` of `token` held by the contract to `to`
+        fn send(
+            &self,
+            (token, amount): (Token, Balance),
+            to: AccountId,
+        ) -> Result<(), EscrowError> {
+            match token {
+                Token::Native => self.transfer_native(to, amount),
+                Token::Asset(asset_id) => Ok(AssetsExtension::transfer(
+                    assets_extension::Origin::Address,
+                    asset_id,
+                    to,
+                    amount,
+                )?),
+            }
+        }
+
+        fn transfer_native(&self, to: AccountId, amount: Balance) -> Result<(), EscrowError> {
+            self.env()
+                .transfer(to, amount)
+                .map_err(|_| EscrowError::TransferFailed)
+        }
+
+        fn expire_call(&self, offer_id: u64) -> ContractCallInput {
+            let mut data = Selector::new(ink::selector_bytes!("expire"))
+                .to_bytes()
+                .to_vec();
+            scale::Encode::encode_to(&offer_id, &mut data);
+            ContractCallInput {
+                dest: self.env().account_id(),
+                data,
+                gas_limit: CALL_GAS_LIMIT,
+                storage_deposit_limit: None,
+                value: 0,
+                max_weight: CALL_MAX_WEIGHT,
+            }
+        }
+    }
+
+    impl Default for OtcEscrow {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink::env::{test, DefaultEnvironment};
+        use scheduler_extension::mock::Mocks;
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const X: u128 = 1;
+        const Y: u128 = 2;
+
+        fn setup() -> (OtcEscrow, Mocks, test::DefaultAccounts<DefaultEnvironment>) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mocks = Mocks::register(AccountId::from(CONTRACT));
+            for (asset_id, holder) in [(X, accounts.alice), (Y, accounts.bob)] {
+                mocks.assets.create(asset_id, holder, 1);
+                mocks.assets.mint(asset_id, holder, 1_000);
+                mocks
+                    .assets
+                    .approve(asset_id, holder, AccountId::from(CONTRACT), 1_000);
+            }
+            test::set_account_balance::<DefaultEnvironment>(AccountId::from(CONTRACT), 0);
+            mocks.set_caller(accounts.alice);
+            (OtcEscrow::new(), mocks, accounts)
+        }
+
+        /// Value transferred by the caller, credited to the contract like the
+        /// runtime does before the call
+        fn pay(value: Balance) {
+            let contract = AccountId::from(CONTRACT);
+            let balance = test::get_account_balance::<DefaultEnvironment>(contract).unwrap();
+            test::set_account_balance::<DefaultEnvironment>(contract, balance + value);
+            test::set_value_transferred::<DefaultEnvironment>(value);
+        }
+
+        #[ink::test]
+        fn swaps_assets_atomically() {
+            let (mut escrow, mocks, accounts) = setup();
+            let first = escrow
+                .make_offer((Token::Asset(X), 100), (Token::Asset(Y), 300), None, 10)
+                .unwrap();
+            let second = escrow
+                .make_offer((Token::Asset(X), 50), (Token::Asset(Y), 50), None, 10)
+                .unwrap();
+            assert_eq!(escrow.open_count(), 2);
+            assert_eq!(mocks.assets.balance_of(X, AccountId::from(CONTRACT)), 150);
+
+            mocks.set_caller(accounts.bob);
+            assert_eq!(escrow.take_offer(first), Ok(()));
+            assert_eq!(mocks.assets.balance_of(X, accounts.bob), 100);
+            assert_eq!(mocks.assets.balance_of(Y, accounts.alice), 300);
+            assert_eq!(escrow.take_offer(first), Err(EscrowError::OfferNotFound));
+            assert_eq!(
+                escrow.offers(0, 10),
+                [(second, escrow.offer(second).unwrap())]
+            );
+
+            // the expiry of the taken offer still runs, and only the open one
+            // is refunded
+            let due = mocks.scheduler.run_to_block(10);
+            assert_eq!(due.len(), 2);
+            assert_eq!(due[0].1.call, escrow.expire_call(first));
+            assert_eq!(escrow.expire(first), Err(EscrowError::OfferNotFound));
+            assert_eq!(escrow.expire(second), Ok(()));
+            assert_eq!(mocks.assets.balance_of(X, accounts.alice), 900);
+        }
+
+        #[ink::test]
+        fn cancel_returns_locked_token() {
+            let (mut escrow, mocks, accounts) = setup();
+            let offer_id = escrow
+                .make_offer((Token::Asset(X), 100), (Token::Native, 500), None, 10)
+                .unwrap();
+
+            mocks.set_caller(accounts.bob);
+            assert_eq!(escrow.cancel_offer(offer_id), Err(EscrowError::NotMaker));
+            mocks.set_caller(accounts.alice);
+            assert_eq!(escrow.cancel_offer(offer_id), Ok(()));
+            assert_eq!(mocks.assets.balance_of(X, accounts.alice), 1_000);
+            assert_eq!(escrow.open_count(), 0);
+
+            assert_eq!(mocks.scheduler.run_to_block(10).len(), 1);
+            assert_eq!(escrow.expire(offer_id), Err(EscrowError::OfferNotFound));
+        }
+
+        #[ink::test]
+        fn sells_asset_for_native() {
+            let (mut escrow, mocks, accounts) = setup();
+            let offer_id = escrow
+                .make_offer(
+                    (Token::Asset(X), 100),
+                    (Token::Native, 500),
+                    Some(accounts.charlie),
+                    10,
+                )
+                .unwrap();
+
+            mocks.set_caller(accounts.bob);
+            pay(500);
+            assert_eq!(escrow.take_offer(offer_id), Err(EscrowError::NotTaker));
+            mocks.set_caller(accounts.charlie);
+            pay(499);
+            assert_eq!(escrow.take_offer(offer_id), Err(EscrowError::WrongValue));
+
+            let alice_balance =
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+            pay(500);
+            assert_eq!(escrow.take_offer(offer_id), Ok(()));
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice),
+                Ok(alice_balance + 500)
+            );
+            assert_eq!(mocks.assets.balance_of(X, accounts.charlie), 100);
+        }
+
+        #[ink::test]
+        fn scheduler_expires_stale_offers() {
+            let (mut escrow, mocks, accounts) = setup();
+            mocks.set_caller(accounts.bob);
+            pay(700);
+            let offer_id = escrow
+                .make_offer((Token::Native, 700), (Token::Asset(X), 10), None, 5)
+                .unwrap();
+            let bob_balance =
+                test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap();
+            assert_eq!(escrow.expire(offer_id), Err(EscrowError::NotExpired));
+
+            let due = mocks.scheduler.run_to_block(5);
+            assert_eq!(due.len(), 1);
+            assert_eq!(due[0].1.origin, AccountId::from(CONTRACT));
+            assert_eq!(due[0].1.call, escrow.expire_call(offer_id));
+            mocks.set_caller(accounts.alice);
+            test::set_value_transferred::<DefaultEnvironment>(0);
+            assert_eq!(escrow.take_offer(offer_id), Err(EscrowError::Expired));
+
+            // dispatched by the scheduler
+            assert_eq!(escrow.expire(offer_id), Ok(()));
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.bob),
+                Ok(bob_balance + 700)
+            );
+            assert_eq!(escrow.open_count(), 0);
+            assert_eq!(escrow.expire(offer_id), Err(EscrowError::OfferNotFound));
+        }
+    }
+}
@@ -0,0 +1,291 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod payment_splitter {
+    use assets_extension::*;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use sp_arithmetic::helpers_128bit::multiply_by_rational_with_rounding;
+    use sp_arithmetic::Rounding;
+
+    /// Maximum number of payees
+    pub const MAX_PAYEES: u32 = 32;
+
+    /// Asset id, `None` for the native currency
+    type Currency = Option<u128>;
+
+    #[ink(event)]
+    pub struct Released {
+        #[ink(topic)]
+        payee: AccountId,
+        /// `None` for the native currency
+        #[ink(topic)]
+        asset_id: Option<u128>,
+        amount: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum SplitterError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        /// Payees are empty, more than `MAX_PAYEES`, not unique or without shares
+        InvalidPayees,
+        NotPayee,
+        /// Nothing is due to the payee
+        NothingToRelease,
+        /// Native transfer failed
+        TransferFailed,
+        Overflow,
+    }
+
+    impl From<AssetsError> for SplitterError {
+        fn from(error: AssetsError) -> Self {
+            SplitterError::Assets(error)
+        }
+    }
+
+    /// Splits everything it receives, in any pallet asset or in the native
+    /// currency, between payees in proportion of their shares.
+    ///
+    /// Payments are not pushed: each payee releases its portion of what the
+    /// contract received so far, which is its balance plus what was already
+    /// released.
+    #[ink(storage)]
+    pub struct PaymentSplitter {
+        payees: Vec<AccountId>,
+        shares: Mapping<AccountId, u128>,
+        total_shares: u128,
+        /// Released per currency
+        total_released: Mapping<Currency, Balance>,
+        /// Released per currency and payee
+        released: Mapping<(Currency, AccountId), Balance>,
+    }
+
+    impl PaymentSplitter {
+        #[ink(constructor)]
+        pub fn new(payees: Vec<(AccountId, u128)>) -> Result<Self, SplitterError> {
+            if payees.is_empty()
+                || payees.len() as u32 > MAX_PAYEES
+                || payees.iter().any(|(_, shares)| *shares == 0)
+                || payees
+                    .iter()
+                    .enumerate()
+                    .any(|(index, (payee, _))| payees[..index].iter().any(|(p, _)| p == payee))
+            {
+                return Err(SplitterError::InvalidPayees);
+            }
+            let mut shares = Mapping::default();
+            let mut total_shares: u128 = 0;
+            for (payee, payee_shares) in &payees {
+                shares.insert(payee, payee_shares);
+                total_shares = total_shares
+                    .checked_add(*payee_shares)
+                    .ok_or(SplitterError::Overflow)?;
+            }
+            Ok(Self {
+                payees: payees.into_iter().map(|(payee, _)| payee).collect(),
+                shares,
+                total_shares,
+                total_released: Mapping::default(),
+                released: Mapping::default(),
+            })
+        }
+
+        #[ink(message)]
+        pub fn payees(&self) -> Vec<AccountId> {
+            self.payees.clone()
+        }
+
+        #[ink(message)]
+        pub fn shares(&self, payee: AccountId) -> u128 {
+            self.shares.get(payee).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn total_shares(&self) -> u128 {
+            self.total_shares
+        }
+
+        /// Released to `payee` so far, `None` for the native currency
+        #[ink(message)]
+        pub fn released(&self, asset_id: Option<u128>, payee: AccountId) -> Balance {
+            self.released.get((asset_id, payee)).unwrap_or_default()
+        }
+
+        /// Released to all payees so far, `None` for the native currency
+        #[ink(message)]
+        pub fn total_released(&self, asset_id: Option<u128>) -> Balance {
+            self.total_released.get(asset_id).unwrap_or_default()
+        }
+
+        /// Amount `payee` can release now, `None` for the native currency
+        #[ink(message)]
+        pub fn releasable(&self, asset_id: Option<u128>, payee: AccountId) -> Balance {
+            let received = self.balance(asset_id) + self.total_released(asset_id);
+            multiply_by_rational_with_rounding(
+                received,
+                self.shares(payee),
+                self.total_shares,
+                Rounding::Down,
+            )
+            .unwrap_or_default()
+            .saturating_sub(self.released(asset_id, payee))
+        }
+
+        /// Sends the caller its portion of `asset_id`
+        #[ink(message)]
+        pub fn release(&mut self, asset_id: u128) -> Result<Balance, SplitterError> {
+            self.release_to_caller(Some(asset_id))
+        }
+
+        /// Sends the caller its portion of the native currency
+        #[ink(message)]
+        pub fn release_native(&mut self) -> Result<Balance, SplitterError> {
+            self.release_to_caller(None)
+        }
+
+        fn release_to_caller(&mut self, asset_id: Currency) -> Result<Balance, SplitterError> {
+            let payee = self.env().caller();
+            if !self.shares.contains(payee) {
+                return Err(SplitterError::NotPayee);
+            }
+            let amount = self.releasable(asset_id, payee);
+            if amount == 0 {
+                return Err(SplitterError::NothingToRelease);
+            }
+            self.released.insert(
+                (asset_id, payee),
+                &(self.released(asset_id, payee) + amount),
+            );
+            self.total_released
+                .insert(asset_id, &(self.total_released(asset_id) + amount));
+            match asset_id {
+                Some(asset_id) => {
+                    AssetsExtension::transfer(Origin::Address, asset_id, payee, amount)?
+                }
+                None => self
+                    .env()
+                    .transfer(payee, amount)
+                    .map_err(|_| SplitterError::TransferFailed)?,
+            }
+            self.env().emit_event(Released {
+                payee,
+                asset_id,
+                amount,
+            });
+            Ok(amount)
+        }
+
+        /// Balance held in `asset_id`, above the existential deposit for the
+        /// native currency as that part cannot be sent
+        fn balance(&self, asset_id: Currency) -> Balance {
+            match asset_id {
+                Some(asset_id) => AssetsExtension::balance_of(asset_id, self.env().account_id()),
+                None => self
+                    .env()
+                    .balance()
+                    .saturating_sub(self.env().minimum_balance()),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use assets_extension::mock::AssetsMock;
+        use ink::env::{test, DefaultEnvironment};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const ASSET: u128 = 1;
+
+        fn setup() -> (
+            PaymentSplitter,
+            AssetsMock,
+            test::DefaultAccounts<DefaultEnvironment>,
+        ) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mock = AssetsMock::register(AccountId::from(CONTRACT));
+            mock.create(ASSET, accounts.alice, 1);
+            test::set_account_balance::<DefaultEnvironment>(
+                AccountId::from(CONTRACT),
+                ink::env::minimum_balance::<DefaultEnvironment>(),
+            );
+            let splitter = PaymentSplitter::new(vec![
+                (accounts.bob, 1),
+                (accounts.charlie, 1),
+                (accounts.django, 2),
+            ])
+            .unwrap();
+            (splitter, mock, accounts)
+        }
+
+        #[ink::test]
+        fn releases_pro_rata_across_payments() {
+            let (mut splitter, mock, accounts) = setup();
+            mock.mint(ASSET, AccountId::from(CONTRACT), 400);
+
+            mock.set_caller(accounts.bob);
+            assert_eq!(splitter.release(ASSET), Ok(100));
+            assert_eq!(
+                splitter.release(ASSET),
+                Err(SplitterError::NothingToRelease)
+            );
+
+            mock.mint(ASSET, AccountId::from(CONTRACT), 800);
+            assert_eq!(splitter.releasable(Some(ASSET), accounts.bob), 200);
+            assert_eq!(splitter.release(ASSET), Ok(200));
+            mock.set_caller(accounts.django);
+            assert_eq!(splitter.release(ASSET), Ok(600));
+            mock.set_caller(accounts.charlie);
+            assert_eq!(splitter.release(ASSET), Ok(300));
+
+            assert_eq!(mock.balance_of(ASSET, accounts.bob), 300);
+            assert_eq!(mock.balance_of(ASSET, AccountId::from(CONTRACT)), 0);
+            assert_eq!(splitter.total_released(Some(ASSET)), 1_200);
+            mock.set_caller(accounts.eve);
+            assert_eq!(splitter.release(ASSET), Err(SplitterError::NotPayee));
+        }
+
+        #[ink::test]
+        fn releases_native_balance() {
+            let (mut splitter, mock, accounts) = setup();
+            assert_eq!(splitter.releasable(None, accounts.bob), 0);
+            // the existential deposit stays in the contract
+            let minimum_balance = ink::env::minimum_balance::<DefaultEnvironment>();
+            test::set_account_balance::<DefaultEnvironment>(
+                AccountId::from(CONTRACT),
+                minimum_balance + 1_000,
+            );
+
+            mock.set_caller(accounts.django);
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.django).unwrap();
+            assert_eq!(splitter.release_native(), Ok(500));
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.django),
+                Ok(balance + 500)
+            );
+            assert_eq!(splitter.releasable(None, accounts.bob), 250);
+            assert_eq!(splitter.releasable(Some(ASSET), accounts.bob), 0);
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(AccountId::from(CONTRACT)),
+                Ok(minimum_balance + 500)
+            );
+        }
+
+        #[ink::test]
+        fn rejects_invalid_payees() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let new = |payees| PaymentSplitter::new(payees).err();
+            assert_eq!(new(vec![]), Some(SplitterError::InvalidPayees));
+            assert_eq!(
+                new(vec![(accounts.bob, 1), (accounts.bob, 2)]),
+                Some(SplitterError::InvalidPayees)
+            );
+            assert_eq!(
+                new(vec![(accounts.bob, 1), (accounts.charlie, 0)]),
+                Some(SplitterError::InvalidPayees)
+            );
+        }
+    }
+}
@@ -0,0 +1,440 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod payment_streams {
+    use assets_extension::{AssetsError, AssetsExtension};
+    use ink::env::call::Selector;
+    use ink::storage::Mapping;
+    use scheduler_extension::{
+        ContractCallInput, SchedulerError, SchedulerExtension, CALL_GAS_LIMIT, CALL_MAX_WEIGHT,
+    };
+
+    #[ink(event)]
+    pub struct StreamCreated {
+        #[ink(topic)]
+        stream_id: u64,
+        #[ink(topic)]
+        payer: AccountId,
+        #[ink(topic)]
+        recipient: AccountId,
+        stream: Stream,
+    }
+
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        stream_id: u64,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct StreamCancelled {
+        #[ink(topic)]
+        stream_id: u64,
+        /// Sent to the recipient
+        accrued: Balance,
+        /// Sent back to the payer
+        refund: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum StreamError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        /// Error from the scheduler pallet
+        Scheduler(SchedulerError),
+        /// Rate, duration or period is zero
+        ZeroAmount,
+        Overflow,
+        /// The stream would start before the current block
+        StartInPast,
+        StreamNotFound,
+        NotPayer,
+        /// Caller is neither the payer nor the recipient of the stream
+        NotParty,
+        /// Nothing has accrued since the last withdrawal
+        NothingToWithdraw,
+        /// The stream has already ended
+        Ended,
+    }
+
+    impl From<AssetsError> for StreamError {
+        fn from(error: AssetsError) -> Self {
+            StreamError::Assets(error)
+        }
+    }
+
+    impl From<SchedulerError> for StreamError {
+        fn from(error: SchedulerError) -> Self {
+            StreamError::Scheduler(error)
+        }
+    }
+
+    /// `rate` of `asset_id` per block from `start` to `stop`, escrowed upfront
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Stream {
+        pub payer: AccountId,
+        pub recipient: AccountId,
+        pub asset_id: u128,
+        pub rate: Balance,
+        pub start: BlockNumber,
+        pub stop: BlockNumber,
+        pub withdrawn: Balance,
+    }
+
+    impl Stream {
+        pub fn deposit(&self) -> Balance {
+            self.rate * (self.stop - self.start) as Balance
+        }
+
+        /// Amount streamed at block `now`, withdrawn or not
+        pub fn streamed(&self, now: BlockNumber) -> Balance {
+            let elapsed = now.clamp(self.start, self.stop) - self.start;
+            self.rate * elapsed as Balance
+        }
+    }
+
+    /// Streams pallet assets from payers to recipients block by block.
+    #[ink(storage)]
+    pub struct PaymentStreams {
+        streams: Mapping<u64, Stream>,
+        next_stream_id: u64,
+    }
+
+    impl PaymentStreams {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                streams: Mapping::default(),
+                next_stream_id: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn stream(&self, stream_id: u64) -> Option<Stream> {
+            self.streams.get(stream_id)
+        }
+
+        /// Amount the recipient of the stream can withdraw now
+        #[ink(message)]
+        pub fn withdrawable(&self, stream_id: u64) -> Balance {
+            self.streams.get(stream_id).map_or(0, |stream| {
+                stream.streamed(self.env().block_number()) - stream.withdrawn
+            })
+        }
+
+        /// Streams `rate` of `asset_id` per block to `recipient` for `duration`
+        /// blocks from `start`, at the earliest the current block, escrowing
+        /// `rate * duration` from the caller.
+        /// Caller should approve contract address as spender beforehand
+        #[ink(message)]
+        pub fn create_stream(
+            &mut self,
+            recipient: AccountId,
+            asset_id: u128,
+            rate: Balance,
+            start: BlockNumber,
+            duration: BlockNumber,
+        ) -> Result<u64, StreamError> {
+            if rate == 0 || duration == 0 {
+                return Err(StreamError::ZeroAmount);
+            }
+            if start < self.env().block_number() {
+                return Err(StreamError::StartInPast);
+            }
+            let stop = start.checked_add(duration).ok_or(StreamError::Overflow)?;
+            let deposit = rate
+                .checked_mul(duration as Balance)
+                .ok_or(StreamError::Overflow)?;
+            let payer = self.env().caller();
+            AssetsExtension::transfer_approved(
+                assets_extension::Origin::Address,
+                asset_id,
+                payer,
+                self.env().account_id(),
+                deposit,
+            )?;
+
+            let stream_id = self.next_stream_id;
+            self.next_stream_id += 1;
+            let stream = Stream {
+                payer,
+                recipient,
+                asset_id,
+                rate,
+                start,
+                stop,
+                withdrawn: 0,
+            };
+            self.streams.insert(stream_id, &stream);
+            self.env().emit_event(StreamCreated {
+                stream_id,
+                payer,
+                recipient,
+                stream,
+            });
+            Ok(stream_id)
+        }
+
+        /// Sends what has accrued to the recipient of the stream. Anyone can
+        /// call it, e.g. the scheduler.
+        #[ink(message)]
+        pub fn withdraw(&mut self, stream_id: u64) -> Result<Balance, StreamError> {
+            let mut stream = self
+                .streams
+                .get(stream_id)
+                .ok_or(StreamError::StreamNotFound)?;
+            let streamed = stream.streamed(self.env().block_number());
+            let value = streamed - stream.withdrawn;
+            if value == 0 {
+                return Err(StreamError::NothingToWithdraw);
+            }
+            stream.withdrawn = streamed;
+            if stream.withdrawn == stream.deposit() {
+                self.streams.remove(stream_id);
+            } else {
+                self.streams.insert(stream_id, &stream);
+            }
+            AssetsExtension::transfer(
+                assets_extension::Origin::Address,
+                stream.asset_id,
+                stream.recipient,
+                value,
+            )?;
+            self.env().emit_event(Withdrawn { stream_id, value });
+            Ok(value)
+        }
+
+        /// Stops the stream, sending what has accrued to the recipient and the
+        /// remainder back to the payer
+        #[ink(message)]
+        pub fn cancel(&mut self, stream_id: u64) -> Result<(), StreamError> {
+            let stream = self
+                .streams
+                .get(stream_id)
+                .ok_or(StreamError::StreamNotFound)?;
+            if self.env().caller() != stream.payer {
+                return Err(StreamError::NotPayer);
+            }
+            let streamed = stream.streamed(self.env().block_number());
+            let accrued = streamed - stream.withdrawn;
+            let refund = stream.deposit() - streamed;
+            self.streams.remove(stream_id);
+            for (to, value) in [(stream.recipient, accrued), (stream.payer, refund)] {
+                if value > 0 {
+                    AssetsExtension::transfer(
+                        assets_extension::Origin::Address,
+                        stream.asset_id,
+                        to,
+                        value,
+                    )?;
+                }
+            }
+            self.env().emit_event(StreamCancelled {
+                stream_id,
+                accrued,
+                refund,
+            });
+            Ok(())
+        }
+
+        /// Schedules `withdraw(stream_id)` every `period` blocks from now until
+        /// the stream stops. The calls are dispatched as the caller, the payer
+        /// or the recipient, who pays their storage deposit. Calls left after
+        /// the stream is cancelled fail with `StreamNotFound`.
+        #[ink(message)]
+        pub fn schedule_payouts(
+            &mut self,
+            stream_id: u64,
+            period: BlockNumber,
+        ) -> Result<(), StreamError> {
+            let stream = self
+                .streams
+                .get(stream_id)
+                .ok_or(StreamError::StreamNotFound)?;
+            let caller = self.env().caller();
+            if caller != stream.payer && caller != stream.recipient {
+                return Err(StreamError::NotParty);
+            }
+            if period == 0 {
+                return Err(StreamError::ZeroAmount);
+            }
+            let from = self.env().block_number().max(stream.start);
+            if from >= stream.stop {
+                return Err(StreamError::Ended);
+            }
+            // the last payout is at or right after `stop`
+            let blocks = stream.stop - from;
+            let count = blocks / period + u32::from(blocks % period != 0);
+            let first = from.checked_add(period).ok_or(StreamError::Overflow)?;
+            SchedulerExtension::schedule(
+                scheduler_extension::Origin::Caller,
+                first,
+                Some((period, count)),
+                0,
+                self.withdraw_call(stream_id),
+            )?;
+            Ok(())
+        }
+
+        fn withdraw_call(&self, stream_id: u64) -> ContractCallInput {
+            let mut data = Selector::new(ink::selector_bytes!("withdraw"))
+                .to_bytes()
+                .to_vec();
+            scale::Encode::encode_to(&stream_id, &mut data);
+            ContractCallInput {
+                dest: self.env().account_id(),
+                data,
+                gas_limit: CALL_GAS_LIMIT,
+                storage_deposit_limit: None,
+                value: 0,
+                max_weight: CALL_MAX_WEIGHT,
+            }
+        }
+    }
+
+    impl Default for PaymentStreams {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink::env::{test, DefaultEnvironment};
+        use scheduler_extension::mock::Mocks;
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const ASSET: u128 = 1;
+
+        fn setup() -> (
+            PaymentStreams,
+            Mocks,
+            test::DefaultAccounts<DefaultEnvironment>,
+        ) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mocks = Mocks::register(AccountId::from(CONTRACT));
+            mocks.assets.create(ASSET, accounts.alice, 1);
+            mocks.assets.mint(ASSET, accounts.alice, 1_000);
+            mocks
+                .assets
+                .approve(ASSET, accounts.alice, AccountId::from(CONTRACT), 1_000);
+            mocks.set_caller(accounts.alice);
+            (PaymentStreams::new(), mocks, accounts)
+        }
+
+        #[ink::test]
+        fn recipient_withdraws_accrued() {
+            let (mut streams, mocks, accounts) = setup();
+            let stream_id = streams
+                .create_stream(accounts.bob, ASSET, 10, 5, 20)
+                .unwrap();
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.alice), 800);
+            assert_eq!(
+                streams.withdraw(stream_id),
+                Err(StreamError::NothingToWithdraw)
+            );
+
+            mocks.scheduler.run_to_block(12);
+            assert_eq!(streams.withdrawable(stream_id), 70);
+            assert_eq!(streams.withdraw(stream_id), Ok(70));
+            mocks.scheduler.run_to_block(100);
+            assert_eq!(streams.withdraw(stream_id), Ok(130));
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.bob), 200);
+            assert_eq!(streams.stream(stream_id), None);
+        }
+
+        #[ink::test]
+        fn payer_cancels_and_reclaims_remainder() {
+            let (mut streams, mocks, accounts) = setup();
+            let stream_id = streams
+                .create_stream(accounts.bob, ASSET, 10, 0, 20)
+                .unwrap();
+            mocks.scheduler.run_to_block(5);
+            streams.withdraw(stream_id).unwrap();
+            mocks.scheduler.run_to_block(8);
+
+            mocks.set_caller(accounts.bob);
+            assert_eq!(streams.cancel(stream_id), Err(StreamError::NotPayer));
+            mocks.set_caller(accounts.alice);
+            assert_eq!(streams.cancel(stream_id), Ok(()));
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.bob), 80);
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.alice), 920);
+            assert_eq!(mocks.assets.balance_of(ASSET, AccountId::from(CONTRACT)), 0);
+        }
+
+        #[ink::test]
+        fn scheduler_pushes_payouts() {
+            let (mut streams, mocks, accounts) = setup();
+            let stream_id = streams
+                .create_stream(accounts.bob, ASSET, 10, 0, 25)
+                .unwrap();
+
+            mocks.set_caller(accounts.charlie);
+            assert_eq!(
+                streams.schedule_payouts(stream_id, 10),
+                Err(StreamError::NotParty)
+            );
+            mocks.set_caller(accounts.bob);
+            streams.schedule_payouts(stream_id, 10).unwrap();
+            for (block, value) in [(10, 100), (20, 100), (30, 50)] {
+                let due = mocks.scheduler.run_to_block(block);
+                assert_eq!(due.len(), 1);
+                assert_eq!(due[0].1.origin, accounts.bob);
+                assert_eq!(due[0].1.call, streams.withdraw_call(stream_id));
+                // dispatched by the scheduler
+                assert_eq!(streams.withdraw(stream_id), Ok(value));
+            }
+            assert!(mocks.scheduler.run_to_block(100).is_empty());
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.bob), 250);
+        }
+
+        #[ink::test]
+        fn scheduling_payouts_needs_caller_origin() {
+            let (mut streams, mocks, accounts) = setup();
+            let stream_id = streams
+                .create_stream(accounts.bob, ASSET, 10, 0, 25)
+                .unwrap();
+            mocks.scheduler.reject_caller_origin();
+
+            mocks.set_caller(accounts.bob);
+            assert_eq!(
+                streams.schedule_payouts(stream_id, 10),
+                Err(StreamError::Scheduler(SchedulerError::OriginCannotBeCaller))
+            );
+            // payouts can still be withdrawn by hand
+            mocks.scheduler.run_to_block(10);
+            assert_eq!(streams.withdraw(stream_id), Ok(100));
+        }
+
+        #[ink::test]
+        fn invalid_streams_are_rejected() {
+            let (mut streams, mocks, accounts) = setup();
+            mocks.scheduler.run_to_block(5);
+            assert_eq!(
+                streams.create_stream(accounts.bob, ASSET, 10, 4, 20),
+                Err(StreamError::StartInPast)
+            );
+            assert_eq!(
+                streams.create_stream(accounts.bob, ASSET, 10, BlockNumber::MAX, 1),
+                Err(StreamError::Overflow)
+            );
+
+            let stream_id = streams
+                .create_stream(accounts.bob, ASSET, 10, BlockNumber::MAX - 10, 10)
+                .unwrap();
+            for period in [20, BlockNumber::MAX] {
+                assert_eq!(
+                    streams.schedule_payouts(stream_id, period),
+                    Err(StreamError::Overflow)
+                );
+            }
+        }
+    }
+}
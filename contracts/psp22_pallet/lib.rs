@@ -0,0 +1,254 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// PSP22 interface over a pallet-assets asset.
+///
+/// Unlike `psp22_pallet_wrapper` the contract keeps no balances of its own: every
+/// query reads the pallet and every transfer or approval is dispatched with
+/// `Origin::Caller`, i.e. on behalf of the account calling the contract.
+/// For the same reason it has no signed permits: a pallet approval can only be
+/// dispatched by the owner itself, not by whoever submits the permit.
+#[openbrush::contract]
+pub mod psp22_pallet {
+    use assets_extension::Origin;
+    use assets_extension::*;
+    use ink::codegen::{EmitEvent, Env};
+    use ink::prelude::vec::Vec;
+    use openbrush::contracts::traits::psp22::*;
+
+    #[ink(event)]
+    pub struct Transfer {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        spender: AccountId,
+        value: Balance,
+    }
+
+    #[ink(storage)]
+    pub struct PSP22PalletContract {
+        asset_id: u128,
+    }
+
+    impl PSP22 for PSP22PalletContract {
+        #[ink(message)]
+        fn total_supply(&self) -> Balance {
+            AssetsExtension::total_supply(self.asset_id)
+        }
+
+        #[ink(message)]
+        fn balance_of(&self, owner: AccountId) -> Balance {
+            AssetsExtension::balance_of(self.asset_id, owner)
+        }
+
+        #[ink(message)]
+        fn allowance(&self, owner: AccountId, spender: AccountId) -> Balance {
+            AssetsExtension::allowance(self.asset_id, owner, spender)
+        }
+
+        /// `data` is ignored, the pallet has no receiver hook
+        #[ink(message)]
+        fn transfer(
+            &mut self,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            let from = self.env().caller();
+            AssetsExtension::transfer(Origin::Caller, self.asset_id, to, value)
+                .map_err(to_psp22_error)?;
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+            Ok(())
+        }
+
+        /// `data` is ignored, the pallet has no receiver hook
+        #[ink(message)]
+        fn transfer_from(
+            &mut self,
+            from: AccountId,
+            to: AccountId,
+            value: Balance,
+            _data: Vec<u8>,
+        ) -> Result<(), PSP22Error> {
+            AssetsExtension::transfer_approved(Origin::Caller, self.asset_id, from, to, value)
+                .map_err(to_psp22_error)?;
+            self.env().emit_event(Transfer {
+                from: Some(from),
+                to: Some(to),
+                value,
+            });
+            Ok(())
+        }
+
+        #[ink(message)]
+        fn approve(&mut self, spender: AccountId, value: Balance) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            self.set_allowance(owner, spender, value)
+        }
+
+        #[ink(message)]
+        fn increase_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            let allowance = self.allowance(owner, spender);
+            self.set_allowance(owner, spender, allowance.saturating_add(delta_value))
+        }
+
+        #[ink(message)]
+        fn decrease_allowance(
+            &mut self,
+            spender: AccountId,
+            delta_value: Balance,
+        ) -> Result<(), PSP22Error> {
+            let owner = self.env().caller();
+            let allowance = self.allowance(owner, spender);
+            if allowance < delta_value {
+                return Err(PSP22Error::InsufficientAllowance);
+            }
+            self.set_allowance(owner, spender, allowance - delta_value)
+        }
+    }
+
+    impl PSP22PalletContract {
+        #[ink(constructor)]
+        pub fn new(asset_id: u128) -> Self {
+            Self { asset_id }
+        }
+
+        #[ink(message)]
+        pub fn asset_id(&self) -> u128 {
+            self.asset_id
+        }
+
+        /// Pallet approvals add up, so an existing approval is cancelled before
+        /// approving the new amount.
+        fn set_allowance(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+        ) -> Result<(), PSP22Error> {
+            if AssetsExtension::allowance(self.asset_id, owner, spender) > 0 {
+                AssetsExtension::cancel_approval(Origin::Caller, self.asset_id, spender)
+                    .map_err(to_psp22_error)?;
+            }
+            if value > 0 {
+                AssetsExtension::approve_transfer(Origin::Caller, self.asset_id, spender, value)
+                    .map_err(to_psp22_error)?;
+            }
+            self.env().emit_event(Approval {
+                owner,
+                spender,
+                value,
+            });
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use assets_extension::mock::AssetsMock;
+        use ink::env::{test, DefaultEnvironment};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const ASSET: u128 = 1;
+
+        fn setup() -> (
+            PSP22PalletContract,
+            AssetsMock,
+            test::DefaultAccounts<DefaultEnvironment>,
+        ) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mock = AssetsMock::register(AccountId::from(CONTRACT));
+            mock.create(ASSET, accounts.alice, 1);
+            mock.mint(ASSET, accounts.alice, 100);
+            mock.set_caller(accounts.alice);
+            (PSP22PalletContract::new(ASSET), mock, accounts)
+        }
+
+        #[ink::test]
+        fn transfer_moves_pallet_balance() {
+            let (mut contract, mock, accounts) = setup();
+
+            assert_eq!(
+                PSP22::transfer(&mut contract, accounts.bob, 30, Vec::new()),
+                Ok(())
+            );
+            assert_eq!(mock.balance_of(ASSET, accounts.bob), 30);
+            assert_eq!(PSP22::balance_of(&contract, accounts.alice), 70);
+            assert_eq!(PSP22::total_supply(&contract), 100);
+            assert_eq!(
+                PSP22::transfer(&mut contract, accounts.bob, 100, Vec::new()),
+                Err(PSP22Error::InsufficientBalance)
+            );
+        }
+
+        #[ink::test]
+        fn fails_on_runtimes_without_caller_origin() {
+            let (mut contract, mock, accounts) = setup();
+            mock.reject_caller_origin();
+
+            assert_eq!(
+                PSP22::transfer(&mut contract, accounts.bob, 30, Vec::new()),
+                Err(PSP22Error::Custom(String::from("OriginCannotBeCaller")))
+            );
+            assert_eq!(
+                PSP22::approve(&mut contract, accounts.bob, 30),
+                Err(PSP22Error::Custom(String::from("OriginCannotBeCaller")))
+            );
+            assert_eq!(mock.balance_of(ASSET, accounts.alice), 100);
+        }
+
+        #[ink::test]
+        fn approve_replaces_pallet_approval() {
+            let (mut contract, mock, accounts) = setup();
+
+            PSP22::approve(&mut contract, accounts.bob, 50).unwrap();
+            PSP22::approve(&mut contract, accounts.bob, 20).unwrap();
+            assert_eq!(mock.allowance(ASSET, accounts.alice, accounts.bob), 20);
+            PSP22::decrease_allowance(&mut contract, accounts.bob, 5).unwrap();
+            assert_eq!(
+                PSP22::allowance(&contract, accounts.alice, accounts.bob),
+                15
+            );
+
+            mock.set_caller(accounts.bob);
+            assert_eq!(
+                PSP22::transfer_from(
+                    &mut contract,
+                    accounts.alice,
+                    accounts.charlie,
+                    20,
+                    Vec::new()
+                ),
+                Err(PSP22Error::InsufficientAllowance)
+            );
+            PSP22::transfer_from(
+                &mut contract,
+                accounts.alice,
+                accounts.charlie,
+                15,
+                Vec::new(),
+            )
+            .unwrap();
+            assert_eq!(mock.balance_of(ASSET, accounts.charlie), 15);
+        }
+    }
+}
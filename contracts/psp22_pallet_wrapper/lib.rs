@@ -6,7 +6,22 @@ pub mod psp22_pallet_wrapper {
     use assets_extension::Origin;
     use assets_extension::*;
     use ink::codegen::{EmitEvent, Env};
-    use openbrush::{contracts::psp22::*, traits::Storage};
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use openbrush::{
+        contracts::{
+            ownable::*,
+            psp22::extensions::{flashmint::*, metadata::*, wrapper::PSP22Wrapper},
+        },
+        modifiers,
+        traits::{Storage, String},
+    };
+
+    /// Prefix of every signed permit message
+    pub const PERMIT_PREFIX: &[u8] = b"psp22-permit";
+
+    /// Maximum flash loan fee, in basis points
+    pub const MAX_FLASH_FEE: u16 = 10_000;
 
     #[ink(event)]
     pub struct Transfer {
@@ -26,6 +41,24 @@ pub mod psp22_pallet_wrapper {
         value: Balance,
     }
 
+    #[ink(event)]
+    pub struct Deposit {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdraw {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        value: Balance,
+    }
+
     impl psp22::Internal for PSP22WrapperContract {
         fn _emit_transfer_event(
             &self,
@@ -54,19 +87,176 @@ pub mod psp22_pallet_wrapper {
     pub struct PSP22WrapperContract {
         #[storage_field]
         psp22: psp22::Data,
+        #[storage_field]
+        ownable: ownable::Data,
         asset_id: u128,
+        /// Prepended to the pallet asset name, e.g. "Wrapped "
+        name_prefix: String,
+        /// Prepended to the pallet asset symbol, e.g. "w"
+        symbol_prefix: String,
+        /// Nonce of the next permit signed by each owner
+        nonces: Mapping<AccountId, u64>,
+        /// Flash loan fee in basis points, flash loans are disabled when `None`
+        flash_fee: Option<u16>,
     }
 
     impl PSP22 for PSP22WrapperContract {}
 
+    impl Ownable for PSP22WrapperContract {}
+
+    /// Lends newly minted tokens, at most the pallet asset balance held by the
+    /// contract. The receiver has to approve the contract for the amount plus the
+    /// fee, which are both burned from it. Burning the fee leaves pallet asset in
+    /// excess of the total supply, which the owner can `recover`.
+    impl FlashLender for PSP22WrapperContract {
+        #[ink(message)]
+        fn max_flashloan(&mut self, token: AccountId) -> Balance {
+            let contract = self.env().account_id();
+            if token != contract || self.flash_fee.is_none() {
+                return 0;
+            }
+            AssetsExtension::balance_of(self.asset_id, contract)
+        }
+
+        #[ink(message)]
+        fn flash_fee(
+            &self,
+            token: AccountId,
+            amount: Balance,
+        ) -> Result<Balance, FlashLenderError> {
+            if token != self.env().account_id() {
+                return Err(FlashLenderError::WrongTokenAddress);
+            }
+            let fee = self
+                .flash_fee
+                .ok_or(FlashLenderError::Custom(String::from("FlashLoansDisabled")))?;
+            Ok(amount / MAX_FLASH_FEE as Balance * fee as Balance
+                + amount % MAX_FLASH_FEE as Balance * fee as Balance / MAX_FLASH_FEE as Balance)
+        }
+
+        #[ink(message)]
+        fn flashloan(
+            &mut self,
+            receiver_account: AccountId,
+            token: AccountId,
+            amount: Balance,
+            data: Vec<u8>,
+        ) -> Result<(), FlashLenderError> {
+            let fee = self.flash_fee(token, amount)?;
+            if amount > self.max_flashloan(token) {
+                return Err(FlashLenderError::Custom(String::from(
+                    "AmountExceedsMaxFlashloan",
+                )));
+            }
+            self._mint_to(receiver_account, amount)?;
+            self._on_flashloan(receiver_account, token, fee, amount, data)?;
+            let contract = self.env().account_id();
+            let repayment = amount + fee;
+            let allowance = self.allowance(receiver_account, contract);
+            if allowance < repayment {
+                return Err(FlashLenderError::AllowanceDoesNotAllowRefund);
+            }
+            self._approve_from_to(receiver_account, contract, allowance - repayment)?;
+            self._burn_from(receiver_account, repayment)?;
+            Ok(())
+        }
+    }
+
+    impl PSP22Wrapper for PSP22WrapperContract {
+        /// Moves `amount` of the pallet asset from the caller to the contract and
+        /// mints as many tokens to `account`.
+        /// Caller should approve contract address as spender beforehand
+        #[ink(message)]
+        fn deposit_for(&mut self, account: AccountId, amount: Balance) -> Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            let contract = self.env().account_id();
+            AssetsExtension::transfer_approved(
+                Origin::Address,
+                self.asset_id,
+                caller,
+                contract,
+                amount,
+            )
+            .map_err(to_psp22_error)?;
+            self._mint_to(account, amount)?;
+            self.env().emit_event(Deposit {
+                from: caller,
+                to: account,
+                value: amount,
+            });
+            Ok(())
+        }
+
+        /// Burns `amount` tokens of the caller and sends as much of the pallet asset to `account`
+        #[ink(message)]
+        fn withdraw_to(&mut self, account: AccountId, amount: Balance) -> Result<(), PSP22Error> {
+            let caller = self.env().caller();
+            self._burn_from(caller, amount)?;
+            AssetsExtension::transfer(Origin::Address, self.asset_id, account, amount)
+                .map_err(to_psp22_error)?;
+            self.env().emit_event(Withdraw {
+                from: caller,
+                to: account,
+                value: amount,
+            });
+            Ok(())
+        }
+    }
+
+    /// Metadata is read from the pallet on every call, so it follows
+    /// `set_metadata` on the wrapped asset.
+    impl PSP22Metadata for PSP22WrapperContract {
+        #[ink(message)]
+        fn token_name(&self) -> Option<String> {
+            prefixed(
+                &self.name_prefix,
+                AssetsExtension::metadata_name(self.asset_id),
+            )
+        }
+
+        #[ink(message)]
+        fn token_symbol(&self) -> Option<String> {
+            prefixed(
+                &self.symbol_prefix,
+                AssetsExtension::metadata_symbol(self.asset_id),
+            )
+        }
+
+        #[ink(message)]
+        fn token_decimals(&self) -> u8 {
+            AssetsExtension::metadata_decimals(self.asset_id)
+        }
+    }
+
+    /// `None` when the asset has no metadata or it is not valid UTF-8
+    fn prefixed(prefix: &str, value: Vec<u8>) -> Option<String> {
+        if value.is_empty() {
+            return None;
+        }
+        let value = String::from_utf8(value).ok()?;
+        let mut prefixed = String::from(prefix);
+        prefixed.push_str(&value);
+        Some(prefixed)
+    }
+
     impl PSP22WrapperContract {
         #[ink(constructor)]
         pub fn new(asset_id: u128) -> Self {
             let mut instance = Self::default();
+            instance._init_with_owner(Self::env().caller());
             instance.asset_id = asset_id;
             instance
         }
 
+        /// Prefixes the pallet asset name and symbol, e.g. "Wrapped " and "w"
+        #[ink(constructor)]
+        pub fn new_with_prefix(asset_id: u128, name_prefix: String, symbol_prefix: String) -> Self {
+            let mut instance = Self::new(asset_id);
+            instance.name_prefix = name_prefix;
+            instance.symbol_prefix = symbol_prefix;
+            instance
+        }
+
         #[ink(message)]
         pub fn asset_id(&self) -> u128 {
             self.asset_id
@@ -76,24 +266,334 @@ pub mod psp22_pallet_wrapper {
         #[ink(message)]
         pub fn deposit(&mut self, amount: Balance) -> Result<(), PSP22Error> {
             let caller = self.env().caller();
-            let contract = self.env().account_id();
-            AssetsExtension::transfer_approved(
-                Origin::Address,
-                self.asset_id,
-                caller,
-                contract,
-                amount,
-            )
-            .map_err(|_| PSP22Error::Custom("transfer failed".into()))?;
-            self._mint_to(caller, amount)
+            self.deposit_for(caller, amount)
         }
 
         #[ink(message)]
         pub fn withdraw(&mut self, amount: Balance) -> Result<(), PSP22Error> {
             let caller = self.env().caller();
-            self._burn_from(caller, amount)?;
-            AssetsExtension::transfer(Origin::Address, self.asset_id, caller, amount)
-                .map_err(|_| PSP22Error::Custom("transfer failed".into()))
+            self.withdraw_to(caller, amount)
+        }
+
+        /// Mints to `to` the pallet asset held by the contract in excess of the
+        /// total supply, e.g. sent to the contract without `deposit`.
+        /// Returns the minted amount.
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn recover(&mut self, to: AccountId) -> Result<Balance, PSP22Error> {
+            let held = AssetsExtension::balance_of(self.asset_id, self.env().account_id());
+            let surplus = held.saturating_sub(self.total_supply());
+            if surplus > 0 {
+                self._mint_to(to, surplus)?;
+            }
+            Ok(surplus)
+        }
+
+        /// Amount of tokens not backed by the pallet asset held by the contract,
+        /// zero as long as every token can be withdrawn
+        #[ink(message)]
+        pub fn shortfall(&self) -> Balance {
+            let held = AssetsExtension::balance_of(self.asset_id, self.env().account_id());
+            self.total_supply().saturating_sub(held)
+        }
+
+        /// Enables flash loans with a fee in basis points, or disables them with `None`
+        #[ink(message)]
+        #[modifiers(only_owner)]
+        pub fn set_flash_fee(&mut self, fee: Option<u16>) -> Result<(), PSP22Error> {
+            if fee > Some(MAX_FLASH_FEE) {
+                return Err(PSP22Error::Custom(String::from("FeeTooHigh")));
+            }
+            self.flash_fee = fee;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn nonce(&self, owner: AccountId) -> u64 {
+            self.nonces.get(owner).unwrap_or_default()
+        }
+
+        /// Message the owner signs to permit `spender`, the SCALE encoding of
+        /// `(PERMIT_PREFIX, contract, owner, spender, value, nonce, deadline)`
+        #[ink(message)]
+        pub fn permit_message(
+            &self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            nonce: u64,
+            deadline: Timestamp,
+        ) -> Vec<u8> {
+            scale::Encode::encode(&(
+                PERMIT_PREFIX,
+                self.env().account_id(),
+                owner,
+                spender,
+                value,
+                nonce,
+                deadline,
+            ))
+        }
+
+        /// Sets the allowance of `spender` over the tokens of `owner` to `value`
+        /// with an ecdsa signature of `permit_message` by `owner`, so the owner does
+        /// not need to send a transaction. Anyone can submit the permit before
+        /// `deadline`, and each permit can be used once.
+        ///
+        /// Only ecdsa signatures are supported: `owner` must be the account of an
+        /// ecdsa key, i.e. the blake2 hash of the compressed public key. ink! 4 has
+        /// no sr25519 verification, so sr25519 accounts have to `approve` instead.
+        #[ink(message)]
+        pub fn permit(
+            &mut self,
+            owner: AccountId,
+            spender: AccountId,
+            value: Balance,
+            deadline: Timestamp,
+            signature: [u8; 65],
+        ) -> Result<(), PSP22Error> {
+            if self.env().block_timestamp() > deadline {
+                return Err(PSP22Error::Custom(String::from("PermitExpired")));
+            }
+            let nonce = self.nonce(owner);
+            let message = self.permit_message(owner, spender, value, nonce, deadline);
+            if ecdsa_signer::signer(&message, &signature) != Some(owner) {
+                return Err(PSP22Error::Custom(String::from("PermitInvalidSignature")));
+            }
+            self.nonces.insert(owner, &(nonce + 1));
+            self._approve_from_to(owner, spender, value)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use assets_extension::mock::AssetsMock;
+        use ecdsa_signer::{sign, SecretKey};
+        use ink::env::{test, DefaultEnvironment};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const ASSET: u128 = 1;
+
+        fn setup() -> AssetsMock {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mock = AssetsMock::register(AccountId::from(CONTRACT));
+            mock.create(ASSET, accounts.alice, 1);
+            mock
+        }
+
+        #[ink::test]
+        fn metadata_is_read_from_pallet() {
+            let mock = setup();
+            mock.set_metadata(ASSET, b"Astar", b"ASTR", 18);
+            let contract = PSP22WrapperContract::new(ASSET);
+
+            assert_eq!(contract.token_name(), Some(String::from("Astar")));
+            assert_eq!(contract.token_symbol(), Some(String::from("ASTR")));
+            assert_eq!(contract.token_decimals(), 18);
+
+            mock.set_metadata(ASSET, b"Shiden", b"SDN", 12);
+            assert_eq!(contract.token_symbol(), Some(String::from("SDN")));
+            assert_eq!(contract.token_decimals(), 12);
+        }
+
+        #[ink::test]
+        fn metadata_is_prefixed() {
+            let mock = setup();
+            mock.set_metadata(ASSET, b"Astar", b"ASTR", 18);
+            let contract = PSP22WrapperContract::new_with_prefix(
+                ASSET,
+                String::from("Wrapped "),
+                String::from("w"),
+            );
+
+            assert_eq!(contract.token_name(), Some(String::from("Wrapped Astar")));
+            assert_eq!(contract.token_symbol(), Some(String::from("wASTR")));
+        }
+
+        #[ink::test]
+        fn deposit_for_and_withdraw_to() {
+            let mock = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let contract_id = AccountId::from(CONTRACT);
+            mock.mint(ASSET, accounts.alice, 100);
+            mock.approve(ASSET, accounts.alice, contract_id, 100);
+            mock.set_caller(accounts.alice);
+            let mut contract = PSP22WrapperContract::new(ASSET);
+
+            assert_eq!(contract.deposit_for(accounts.bob, 60), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 60);
+            assert_eq!(contract.balance_of(accounts.alice), 0);
+            assert_eq!(mock.balance_of(ASSET, contract_id), 60);
+
+            mock.set_caller(accounts.bob);
+            assert_eq!(contract.withdraw_to(accounts.charlie, 40), Ok(()));
+            assert_eq!(contract.balance_of(accounts.bob), 20);
+            assert_eq!(mock.balance_of(ASSET, accounts.charlie), 40);
+            assert_eq!(mock.balance_of(ASSET, contract_id), 20);
+        }
+
+        #[ink::test]
+        fn deposit_maps_pallet_error() {
+            let mock = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            mock.mint(ASSET, accounts.alice, 10);
+            mock.set_caller(accounts.alice);
+            let mut contract = PSP22WrapperContract::new(ASSET);
+
+            assert_eq!(contract.deposit(10), Err(PSP22Error::InsufficientAllowance));
+            mock.approve(ASSET, accounts.alice, AccountId::from(CONTRACT), 20);
+            assert_eq!(contract.deposit(20), Err(PSP22Error::InsufficientBalance));
+            assert_eq!(contract.total_supply(), 0);
+        }
+
+        #[ink::test]
+        fn owner_recovers_surplus() {
+            let mock = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let contract_id = AccountId::from(CONTRACT);
+            mock.mint(ASSET, accounts.alice, 100);
+            mock.approve(ASSET, accounts.alice, contract_id, 50);
+            mock.set_caller(accounts.alice);
+            let mut contract = PSP22WrapperContract::new(ASSET);
+            contract.deposit(50).unwrap();
+            // sent directly, without minting tokens
+            AssetsExtension::transfer(Origin::Caller, ASSET, contract_id, 30).unwrap();
+
+            mock.set_caller(accounts.bob);
+            assert_eq!(
+                contract.recover(accounts.bob),
+                Err(OwnableError::CallerIsNotOwner.into())
+            );
+            mock.set_caller(accounts.alice);
+            assert_eq!(contract.recover(accounts.bob), Ok(30));
+            assert_eq!(contract.balance_of(accounts.bob), 30);
+            assert_eq!(contract.recover(accounts.bob), Ok(0));
+            assert_eq!(contract.shortfall(), 0);
+        }
+
+        #[ink::test]
+        fn shortfall_is_reported() {
+            let mock = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let contract_id = AccountId::from(CONTRACT);
+            mock.mint(ASSET, accounts.alice, 100);
+            mock.approve(ASSET, accounts.alice, contract_id, 100);
+            mock.set_caller(accounts.alice);
+            let mut contract = PSP22WrapperContract::new(ASSET);
+            contract.deposit(100).unwrap();
+
+            // the asset admin burns from the contract account
+            AssetsExtension::burn(Origin::Caller, ASSET, contract_id, 25).unwrap();
+            assert_eq!(contract.shortfall(), 25);
+            assert_eq!(contract.recover(accounts.alice), Ok(0));
+        }
+
+        #[ink::test]
+        fn permit_sets_allowance_once() {
+            setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let secret = SecretKey::from_slice(&[0x11; 32]).unwrap();
+            let owner = ecdsa_signer::account(&secret);
+            let mut contract = PSP22WrapperContract::new(ASSET);
+
+            let message = contract.permit_message(owner, accounts.bob, 50, 0, 1_000);
+            let signature = sign(&secret, &message);
+            // submitted by a relayer
+            test::set_caller::<DefaultEnvironment>(accounts.charlie);
+            assert_eq!(
+                contract.permit(owner, accounts.bob, 50, 1_000, signature),
+                Ok(())
+            );
+            assert_eq!(contract.allowance(owner, accounts.bob), 50);
+            assert_eq!(contract.nonce(owner), 1);
+            assert_eq!(
+                contract.permit(owner, accounts.bob, 50, 1_000, signature),
+                Err(PSP22Error::Custom(String::from("PermitInvalidSignature")))
+            );
+        }
+
+        #[ink::test]
+        fn permit_checks_signer_and_deadline() {
+            setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let secret = SecretKey::from_slice(&[0x11; 32]).unwrap();
+            let owner = ecdsa_signer::account(&secret);
+            let mut contract = PSP22WrapperContract::new(ASSET);
+
+            let message = contract.permit_message(owner, accounts.bob, 50, 0, 1_000);
+            let signature = sign(&secret, &message);
+            assert_eq!(
+                contract.permit(owner, accounts.bob, 60, 1_000, signature),
+                Err(PSP22Error::Custom(String::from("PermitInvalidSignature")))
+            );
+            assert_eq!(
+                contract.permit(accounts.alice, accounts.bob, 50, 1_000, signature),
+                Err(PSP22Error::Custom(String::from("PermitInvalidSignature")))
+            );
+
+            test::set_block_timestamp::<DefaultEnvironment>(1_001);
+            assert_eq!(
+                contract.permit(owner, accounts.bob, 50, 1_000, signature),
+                Err(PSP22Error::Custom(String::from("PermitExpired")))
+            );
+            assert_eq!(contract.allowance(owner, accounts.bob), 0);
+        }
+
+        #[ink::test]
+        fn flash_loans_are_optional() {
+            let mock = setup();
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let token = AccountId::from(CONTRACT);
+            mock.mint(ASSET, token, 1_000);
+            let mut contract = PSP22WrapperContract::new(ASSET);
+
+            assert_eq!(contract.max_flashloan(token), 0);
+            assert_eq!(
+                contract.flashloan(accounts.bob, token, 10, Vec::new()),
+                Err(FlashLenderError::Custom(String::from("FlashLoansDisabled")))
+            );
+
+            assert_eq!(
+                contract.set_flash_fee(Some(MAX_FLASH_FEE + 1)),
+                Err(PSP22Error::Custom(String::from("FeeTooHigh")))
+            );
+            contract.set_flash_fee(Some(30)).unwrap();
+            assert_eq!(contract.max_flashloan(token), 1_000);
+            assert_eq!(contract.max_flashloan(accounts.bob), 0);
+            assert_eq!(contract.flash_fee(token, 10_000), Ok(30));
+            assert_eq!(contract.flash_fee(token, 333), Ok(0));
+            assert_eq!(
+                contract.flash_fee(accounts.bob, 10_000),
+                Err(FlashLenderError::WrongTokenAddress)
+            );
+            assert_eq!(
+                contract.flashloan(accounts.bob, token, 1_001, Vec::new()),
+                Err(FlashLenderError::Custom(String::from(
+                    "AmountExceedsMaxFlashloan"
+                )))
+            );
+            assert_eq!(contract.total_supply(), 0);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                contract.set_flash_fee(None),
+                Err(OwnableError::CallerIsNotOwner.into())
+            );
+        }
+
+        #[ink::test]
+        fn missing_metadata_is_none() {
+            setup();
+            let contract = PSP22WrapperContract::new_with_prefix(
+                ASSET,
+                String::from("Wrapped "),
+                String::from("w"),
+            );
+
+            assert_eq!(contract.token_name(), None);
+            assert_eq!(contract.token_symbol(), None);
+            assert_eq!(contract.token_decimals(), 0);
         }
     }
 }
@@ -0,0 +1,254 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+#![feature(min_specialization)]
+
+/// Vault wrapping any number of pallet assets as PSP37 tokens.
+///
+/// The token id of a wrapped asset is `Id::U128(asset_id)`, and its metadata is
+/// read from the pallet under the `name`, `symbol` and `decimals` attributes.
+#[openbrush::contract]
+pub mod psp37_pallet_vault {
+    use assets_extension::Origin;
+    use assets_extension::*;
+    use ink::codegen::{EmitEvent, Env};
+    use ink::prelude::{format, string::ToString, vec, vec::Vec};
+    use openbrush::{
+        contracts::psp37::extensions::metadata::*,
+        traits::{Storage, String},
+    };
+
+    #[ink(event)]
+    pub struct TransferSingle {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        id: Id,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct TransferBatch {
+        #[ink(topic)]
+        from: Option<AccountId>,
+        #[ink(topic)]
+        to: Option<AccountId>,
+        ids_amounts: Vec<(Id, Balance)>,
+    }
+
+    #[ink(event)]
+    pub struct Approval {
+        #[ink(topic)]
+        owner: AccountId,
+        #[ink(topic)]
+        operator: AccountId,
+        id: Option<Id>,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Deposit {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        asset_id: u128,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdraw {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        asset_id: u128,
+        value: Balance,
+    }
+
+    impl psp37::Internal for PSP37VaultContract {
+        fn _emit_transfer_event(
+            &self,
+            from: Option<AccountId>,
+            to: Option<AccountId>,
+            id: Id,
+            amount: Balance,
+        ) {
+            self.env().emit_event(TransferSingle {
+                from,
+                to,
+                id,
+                value: amount,
+            });
+        }
+
+        fn _emit_transfer_batch_event(
+            &self,
+            from: Option<AccountId>,
+            to: Option<AccountId>,
+            ids_amounts: Vec<(Id, Balance)>,
+        ) {
+            self.env().emit_event(TransferBatch {
+                from,
+                to,
+                ids_amounts,
+            });
+        }
+
+        fn _emit_approval_event(
+            &self,
+            owner: AccountId,
+            operator: AccountId,
+            id: Option<Id>,
+            value: Balance,
+        ) {
+            self.env().emit_event(Approval {
+                owner,
+                operator,
+                id,
+                value,
+            });
+        }
+    }
+
+    #[ink(storage)]
+    #[derive(Default, Storage)]
+    pub struct PSP37VaultContract {
+        #[storage_field]
+        psp37: psp37::Data,
+    }
+
+    impl PSP37 for PSP37VaultContract {}
+
+    impl PSP37Metadata for PSP37VaultContract {
+        /// `name`, `symbol` and `decimals` of the wrapped asset, `None` for
+        /// other keys or assets without metadata
+        #[ink(message)]
+        fn get_attribute(&self, id: Id, key: String) -> Option<String> {
+            let Id::U128(asset_id) = id else {
+                return None;
+            };
+            let name = AssetsExtension::metadata_name(asset_id);
+            if name.is_empty() {
+                return None;
+            }
+            match key.as_str() {
+                "name" => String::from_utf8(name).ok(),
+                "symbol" => String::from_utf8(AssetsExtension::metadata_symbol(asset_id)).ok(),
+                "decimals" => Some(AssetsExtension::metadata_decimals(asset_id).to_string()),
+                _ => None,
+            }
+        }
+    }
+
+    impl PSP37VaultContract {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Moves `amount` of `asset_id` from the caller to the vault and mints as
+        /// many `Id::U128(asset_id)` tokens to the caller.
+        /// Caller should approve contract address as spender beforehand
+        #[ink(message)]
+        pub fn deposit(&mut self, asset_id: u128, amount: Balance) -> Result<(), PSP37Error> {
+            let caller = self.env().caller();
+            let contract = self.env().account_id();
+            AssetsExtension::transfer_approved(Origin::Address, asset_id, caller, contract, amount)
+                .map_err(assets_error)?;
+            self._mint_to(caller, vec![(Id::U128(asset_id), amount)])?;
+            self.env().emit_event(Deposit {
+                account: caller,
+                asset_id,
+                value: amount,
+            });
+            Ok(())
+        }
+
+        /// Burns `amount` tokens of `asset_id` and sends as much of the asset to the caller
+        #[ink(message)]
+        pub fn withdraw(&mut self, asset_id: u128, amount: Balance) -> Result<(), PSP37Error> {
+            let caller = self.env().caller();
+            self._burn_from(caller, vec![(Id::U128(asset_id), amount)])?;
+            AssetsExtension::transfer(Origin::Address, asset_id, caller, amount)
+                .map_err(assets_error)?;
+            self.env().emit_event(Withdraw {
+                account: caller,
+                asset_id,
+                value: amount,
+            });
+            Ok(())
+        }
+    }
+
+    /// Keeps the pallet error variant, e.g. `Custom("BalanceLow")` or `Custom("Unapproved")`
+    fn assets_error(error: AssetsError) -> PSP37Error {
+        PSP37Error::Custom(format!("{:?}", error))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use assets_extension::mock::AssetsMock;
+        use ink::env::{test, DefaultEnvironment};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+
+        fn setup() -> (AssetsMock, test::DefaultAccounts<DefaultEnvironment>) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mock = AssetsMock::register(AccountId::from(CONTRACT));
+            for asset_id in [1, 2] {
+                mock.create(asset_id, accounts.alice, 1);
+                mock.mint(asset_id, accounts.alice, 100);
+                mock.approve(asset_id, accounts.alice, AccountId::from(CONTRACT), 100);
+            }
+            mock.set_caller(accounts.alice);
+            (mock, accounts)
+        }
+
+        #[ink::test]
+        fn deposit_and_withdraw_per_asset() {
+            let (mock, accounts) = setup();
+            let mut vault = PSP37VaultContract::new();
+
+            assert_eq!(vault.deposit(1, 40), Ok(()));
+            assert_eq!(vault.deposit(2, 70), Ok(()));
+            assert_eq!(vault.balance_of(accounts.alice, Some(Id::U128(1))), 40);
+            assert_eq!(vault.balance_of(accounts.alice, Some(Id::U128(2))), 70);
+            assert_eq!(vault.total_supply(Some(Id::U128(2))), 70);
+
+            assert_eq!(vault.withdraw(2, 30), Ok(()));
+            assert_eq!(mock.balance_of(2, accounts.alice), 60);
+            assert_eq!(mock.balance_of(2, AccountId::from(CONTRACT)), 40);
+            assert_eq!(mock.balance_of(1, AccountId::from(CONTRACT)), 40);
+        }
+
+        #[ink::test]
+        fn deposit_keeps_pallet_error() {
+            setup();
+            let mut vault = PSP37VaultContract::new();
+
+            assert_eq!(
+                vault.deposit(1, 101),
+                Err(PSP37Error::Custom(String::from("Unapproved")))
+            );
+            assert_eq!(
+                vault.deposit(3, 1),
+                Err(PSP37Error::Custom(String::from("Unknown")))
+            );
+        }
+
+        #[ink::test]
+        fn metadata_is_read_per_asset() {
+            let (mock, _) = setup();
+            mock.set_metadata(1, b"Astar", b"ASTR", 18);
+            let vault = PSP37VaultContract::new();
+
+            let attribute = |id, key: &str| vault.get_attribute(id, String::from(key));
+            assert_eq!(attribute(Id::U128(1), "name"), Some(String::from("Astar")));
+            assert_eq!(attribute(Id::U128(1), "symbol"), Some(String::from("ASTR")));
+            assert_eq!(attribute(Id::U128(1), "decimals"), Some(String::from("18")));
+            assert_eq!(attribute(Id::U128(1), "uri"), None);
+            assert_eq!(attribute(Id::U128(2), "name"), None);
+            assert_eq!(attribute(Id::U128(2), "decimals"), None);
+            assert_eq!(attribute(Id::U8(1), "name"), None);
+        }
+    }
+}
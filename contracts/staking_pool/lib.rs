@@ -0,0 +1,808 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod staking_pool {
+    use dapps_staking_extension::*;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use sp_arithmetic::helpers_128bit::multiply_by_rational_with_rounding;
+    use sp_arithmetic::{Perbill, Rounding};
+
+    /// Maximum number of dApps the pool stakes on
+    pub const MAX_DAPPS: u32 = 16;
+    /// Maximum number of pending unbond requests per account
+    pub const MAX_UNBOND_REQUESTS: u32 = 16;
+    /// Highest fee the operator can take from rewards
+    pub const MAX_FEE: Perbill = Perbill::from_percent(30);
+
+    #[ink(event)]
+    pub struct Deposited {
+        #[ink(topic)]
+        account: AccountId,
+        #[ink(topic)]
+        dapp: AccountId,
+        value: Balance,
+        shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Compounded {
+        era: u32,
+        reward: Balance,
+        fee_shares: Balance,
+    }
+
+    #[ink(event)]
+    pub struct WithdrawRequested {
+        #[ink(topic)]
+        account: AccountId,
+        shares: Balance,
+        value: Balance,
+        unlock_era: u32,
+    }
+
+    #[ink(event)]
+    pub struct Withdrawn {
+        #[ink(topic)]
+        account: AccountId,
+        value: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum StakingPoolError {
+        /// Error from the dapps-staking pallet
+        DappsStaking(DSError),
+        /// Caller is not the pool operator
+        NotOwner,
+        /// Fee is above `MAX_FEE`
+        FeeTooHigh,
+        /// Deposited or redeemed amount is zero
+        ZeroAmount,
+        /// Amount is worth less than one share or one unit of the native token
+        AmountTooSmall,
+        InsufficientShares,
+        /// The pool must stake on at least one dApp
+        NoDapps,
+        TooManyDapps,
+        DappAlreadyAdded,
+        DappNotFound,
+        /// Too many pending unbond requests, withdraw matured ones first
+        TooManyUnbondRequests,
+        /// No unbond request of the caller has matured yet
+        NothingToWithdraw,
+        /// Native transfer to the account failed
+        TransferFailed,
+        /// Rewards of past eras are still unclaimed, call `compound` again
+        CompoundPending,
+    }
+
+    impl From<DSError> for StakingPoolError {
+        fn from(error: DSError) -> Self {
+            StakingPoolError::DappsStaking(error)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct UnbondRequest {
+        pub value: Balance,
+        pub unlock_era: u32,
+    }
+
+    /// Pools native deposits of many accounts and stakes them on a list of dApps.
+    ///
+    /// Every deposit buys shares of the pool. Staker rewards are claimed and restaked once
+    /// per era, the operator takes its fee as newly minted shares, and accounts redeem
+    /// their shares pro-rata after the pallet unbonding period.
+    #[ink(storage)]
+    pub struct StakingPool {
+        owner: AccountId,
+        dapps: Vec<AccountId>,
+        /// Operator fee on rewards, in parts per billion
+        fee: u32,
+        shares: Mapping<AccountId, Balance>,
+        total_shares: Balance,
+        unbond_requests: Mapping<AccountId, Vec<UnbondRequest>>,
+        total_unbonding: Balance,
+        /// Value the pallet unbonded beyond what was redeemed, because the stake
+        /// left on a dApp fell below the minimum. It still belongs to the pool
+        /// and is restaked once withdrawable.
+        surplus_unbonding: Vec<UnbondRequest>,
+        /// Every era before it is claimed on every dApp
+        last_compounded_era: u32,
+    }
+
+    impl StakingPool {
+        #[ink(constructor)]
+        pub fn new(dapps: Vec<AccountId>, fee: Perbill) -> Result<Self, StakingPoolError> {
+            if dapps.is_empty() {
+                return Err(StakingPoolError::NoDapps);
+            }
+            if dapps.len() as u32 > MAX_DAPPS {
+                return Err(StakingPoolError::TooManyDapps);
+            }
+            if fee > MAX_FEE {
+                return Err(StakingPoolError::FeeTooHigh);
+            }
+            Ok(Self {
+                owner: Self::env().caller(),
+                dapps,
+                fee: fee.deconstruct(),
+                shares: Default::default(),
+                total_shares: 0,
+                unbond_requests: Default::default(),
+                total_unbonding: 0,
+                surplus_unbonding: Vec::new(),
+                last_compounded_era: DappsStaking::read_current_era(),
+            })
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn dapps(&self) -> Vec<AccountId> {
+            self.dapps.clone()
+        }
+
+        #[ink(message)]
+        pub fn fee(&self) -> Perbill {
+            Perbill::from_parts(self.fee)
+        }
+
+        #[ink(message)]
+        pub fn shares_of(&self, account: AccountId) -> Balance {
+            self.shares.get(account).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn total_shares(&self) -> Balance {
+            self.total_shares
+        }
+
+        /// Native value staked by the pool over all its dApps, including the
+        /// surplus unbonding to be restaked
+        #[ink(message)]
+        pub fn total_pooled(&self) -> Balance {
+            let surplus: Balance = self
+                .surplus_unbonding
+                .iter()
+                .map(|request| request.value)
+                .sum();
+            self.dapps
+                .iter()
+                .map(|dapp| self.staked_on(*dapp))
+                .sum::<Balance>()
+                + surplus
+        }
+
+        /// Value waiting in unbond requests, not yet paid out
+        #[ink(message)]
+        pub fn total_unbonding(&self) -> Balance {
+            self.total_unbonding
+        }
+
+        #[ink(message)]
+        pub fn last_compounded_era(&self) -> u32 {
+            self.last_compounded_era
+        }
+
+        /// Native value `shares` can currently be redeemed for
+        #[ink(message)]
+        pub fn shares_to_value(&self, shares: Balance) -> Balance {
+            if self.total_shares == 0 {
+                return shares;
+            }
+            Self::mul_div(shares, self.total_pooled(), self.total_shares)
+        }
+
+        /// Shares bought by depositing `value` at the current exchange rate
+        #[ink(message)]
+        pub fn value_to_shares(&self, value: Balance) -> Balance {
+            let pooled = self.total_pooled();
+            if self.total_shares == 0 || pooled == 0 {
+                return value;
+            }
+            Self::mul_div(value, self.total_shares, pooled)
+        }
+
+        #[ink(message)]
+        pub fn unbond_requests(&self, account: AccountId) -> Vec<UnbondRequest> {
+            self.unbond_requests.get(account).unwrap_or_default()
+        }
+
+        /// Stakes the transferred value on the least staked dApp of the pool
+        /// and credits the caller with shares
+        #[ink(message, payable)]
+        pub fn deposit(&mut self) -> Result<Balance, StakingPoolError> {
+            let caller = self.env().caller();
+            let value = self.env().transferred_value();
+            if value == 0 {
+                return Err(StakingPoolError::ZeroAmount);
+            }
+            // pending rewards belong to the current shareholders
+            self.ensure_compounded()?;
+
+            let shares = self.value_to_shares(value);
+            if shares == 0 {
+                return Err(StakingPoolError::AmountTooSmall);
+            }
+            let dapp = self
+                .dapps
+                .iter()
+                .copied()
+                .min_by_key(|dapp| self.staked_on(*dapp))
+                .ok_or(StakingPoolError::NoDapps)?;
+            DappsStaking::bond_and_stake(dapp, value)?;
+            if self.total_shares == 0 {
+                DappsStaking::set_reward_destination(REWARD_DESTINATION_STAKE_BALANCE)?;
+            }
+
+            self.mint(caller, shares);
+            self.env().emit_event(Deposited {
+                account: caller,
+                dapp,
+                value,
+                shares,
+            });
+            Ok(shares)
+        }
+
+        /// Claims staker rewards of up to `max_claims` eras, shared evenly between
+        /// the dApps with at least one era each, which the pallet restakes, and
+        /// mints the operator fee. Anyone can call it, and again until
+        /// `last_compounded_era` reaches the current era: deposits and
+        /// withdrawals wait for it.
+        #[ink(message)]
+        pub fn compound(&mut self, max_claims: u32) -> Result<Balance, StakingPoolError> {
+            self.restake_surplus()?;
+            let contract = self.env().account_id();
+            let shares_before = self.total_shares;
+            let max_eras = (max_claims / self.dapps.len() as u32).max(1);
+            let mut era = DappsStaking::read_current_era();
+            let mut reward: Balance = 0;
+            for dapp in self.dapps.iter() {
+                let staked = self.staked_on(*dapp);
+                match DappsStaking::claim_staker_all(*dapp, max_eras) {
+                    // claims stopped at the limit, so later eras may be left. They
+                    // start at `last_compounded_era` at the earliest.
+                    Ok(claimed) if claimed == max_eras => {
+                        era = era.min(self.last_compounded_era + claimed);
+                    }
+                    Ok(_) | Err(DSError::NotStakedContract) => {}
+                    Err(error) => return Err(error.into()),
+                }
+                reward += DappsStaking::read_staked_amount_on_contract(contract, *dapp)
+                    .saturating_sub(staked);
+            }
+            self.last_compounded_era = era;
+
+            let fee = Perbill::from_parts(self.fee) * reward;
+            let fee_shares = if fee > 0 && shares_before > 0 {
+                // priced against the pool value without the fee itself
+                Self::mul_div(fee, shares_before, self.total_pooled().saturating_sub(fee))
+            } else {
+                0
+            };
+            if fee_shares > 0 {
+                self.mint(self.owner, fee_shares);
+            }
+            self.env().emit_event(Compounded {
+                era,
+                reward,
+                fee_shares,
+            });
+            Ok(reward)
+        }
+
+        /// Redeems `shares` of the caller and starts unbonding their value.
+        /// It can be withdrawn with `withdraw` once the unbonding period passed.
+        #[ink(message)]
+        pub fn request_withdraw(&mut self, shares: Balance) -> Result<Balance, StakingPoolError> {
+            let caller = self.env().caller();
+            if shares == 0 {
+                return Err(StakingPoolError::ZeroAmount);
+            }
+            let balance = self.shares_of(caller);
+            if balance < shares {
+                return Err(StakingPoolError::InsufficientShares);
+            }
+            let mut requests = self.unbond_requests.get(caller).unwrap_or_default();
+            if requests.len() as u32 >= MAX_UNBOND_REQUESTS {
+                return Err(StakingPoolError::TooManyUnbondRequests);
+            }
+            self.ensure_compounded()?;
+
+            let value = self.shares_to_value(shares);
+            if value == 0 {
+                return Err(StakingPoolError::AmountTooSmall);
+            }
+            let unlock_era =
+                DappsStaking::read_current_era() + DappsStaking::read_unbonding_period();
+            self.unstake(value, unlock_era)?;
+
+            self.shares.insert(caller, &(balance - shares));
+            self.total_shares -= shares;
+
+            requests.push(UnbondRequest { value, unlock_era });
+            self.unbond_requests.insert(caller, &requests);
+            self.total_unbonding += value;
+
+            self.env().emit_event(WithdrawRequested {
+                account: caller,
+                shares,
+                value,
+                unlock_era,
+            });
+            Ok(value)
+        }
+
+        /// Pays out every matured unbond request of the caller
+        #[ink(message)]
+        pub fn withdraw(&mut self) -> Result<Balance, StakingPoolError> {
+            let caller = self.env().caller();
+            let current_era = DappsStaking::read_current_era();
+            let requests = self.unbond_requests.get(caller).unwrap_or_default();
+            let (matured, pending): (Vec<_>, Vec<_>) = requests
+                .into_iter()
+                .partition(|request| request.unlock_era <= current_era);
+            let value: Balance = matured.iter().map(|request| request.value).sum();
+            if value == 0 {
+                return Err(StakingPoolError::NothingToWithdraw);
+            }
+
+            // chunks of every account are withdrawn at once, so an earlier caller
+            // may already have moved this value to the contract balance
+            match DappsStaking::withdraw_unbonded() {
+                Ok(()) | Err(DSError::NothingToWithdraw) => {}
+                Err(error) => return Err(error.into()),
+            }
+
+            if pending.is_empty() {
+                self.unbond_requests.remove(caller);
+            } else {
+                self.unbond_requests.insert(caller, &pending);
+            }
+            self.total_unbonding -= value;
+            self.env()
+                .transfer(caller, value)
+                .map_err(|_| StakingPoolError::TransferFailed)?;
+            self.env().emit_event(Withdrawn {
+                account: caller,
+                value,
+            });
+            Ok(value)
+        }
+
+        #[ink(message)]
+        pub fn set_fee(&mut self, fee: Perbill) -> Result<(), StakingPoolError> {
+            self.ensure_owner()?;
+            if fee > MAX_FEE {
+                return Err(StakingPoolError::FeeTooHigh);
+            }
+            // rewards accrued so far are charged with the previous fee
+            self.ensure_compounded()?;
+            self.fee = fee.deconstruct();
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn add_dapp(&mut self, dapp: AccountId) -> Result<(), StakingPoolError> {
+            self.ensure_owner()?;
+            if self.dapps.contains(&dapp) {
+                return Err(StakingPoolError::DappAlreadyAdded);
+            }
+            if self.dapps.len() as u32 >= MAX_DAPPS {
+                return Err(StakingPoolError::TooManyDapps);
+            }
+            self.dapps.push(dapp);
+            Ok(())
+        }
+
+        /// Removes `dapp` from the pool, moving its stake to the most staked remaining
+        /// dApp. When the pallet refuses the move, because the stake on the target
+        /// would be below the minimum, the stake is unbonded and restaked by `compound`.
+        #[ink(message)]
+        pub fn remove_dapp(&mut self, dapp: AccountId) -> Result<(), StakingPoolError> {
+            self.ensure_owner()?;
+            let index = self
+                .dapps
+                .iter()
+                .position(|d| *d == dapp)
+                .ok_or(StakingPoolError::DappNotFound)?;
+            if self.dapps.len() == 1 {
+                return Err(StakingPoolError::NoDapps);
+            }
+            // unclaimed rewards of the dApp would be lost once it is no longer tracked
+            self.ensure_compounded()?;
+            self.dapps.remove(index);
+
+            let staked = self.staked_on(dapp);
+            if staked == 0 {
+                return Ok(());
+            }
+            let target = self
+                .dapps
+                .iter()
+                .copied()
+                .max_by_key(|dapp| self.staked_on(*dapp))
+                .ok_or(StakingPoolError::NoDapps)?;
+            match DappsStaking::nomination_transfer(dapp, target, staked) {
+                Ok(()) => {}
+                Err(DSError::InsufficientValue) => {
+                    DappsStaking::unbond_and_unstake(dapp, staked)?;
+                    let unlock_era =
+                        DappsStaking::read_current_era() + DappsStaking::read_unbonding_period();
+                    self.surplus_unbonding.push(UnbondRequest {
+                        value: staked,
+                        unlock_era,
+                    });
+                }
+                Err(error) => return Err(error.into()),
+            }
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), StakingPoolError> {
+            self.ensure_owner()?;
+            self.owner = new_owner;
+            Ok(())
+        }
+
+        fn ensure_owner(&self) -> Result<(), StakingPoolError> {
+            if self.env().caller() != self.owner {
+                return Err(StakingPoolError::NotOwner);
+            }
+            Ok(())
+        }
+
+        /// Shares are priced once every past era is claimed, so this fails
+        /// with `CompoundPending` until `compound` caught up
+        fn ensure_compounded(&self) -> Result<(), StakingPoolError> {
+            if DappsStaking::read_current_era() > self.last_compounded_era {
+                return Err(StakingPoolError::CompoundPending);
+            }
+            Ok(())
+        }
+
+        fn staked_on(&self, dapp: AccountId) -> Balance {
+            DappsStaking::read_staked_amount_on_contract(self.env().account_id(), dapp)
+        }
+
+        fn mint(&mut self, account: AccountId, shares: Balance) {
+            let balance = self.shares_of(account);
+            self.shares.insert(account, &(balance + shares));
+            self.total_shares += shares;
+        }
+
+        /// Unstakes `value` from the dApps, most staked first. The pallet unstakes
+        /// the whole stake on a dApp when the rest would fall below the minimum,
+        /// the surplus is kept unbonding until `unlock_era` and then restaked.
+        fn unstake(&mut self, value: Balance, unlock_era: u32) -> Result<(), StakingPoolError> {
+            let mut stakes: Vec<(AccountId, Balance)> = self
+                .dapps
+                .iter()
+                .map(|dapp| (*dapp, self.staked_on(*dapp)))
+                .collect();
+            stakes.sort_by_key(|(_, staked)| core::cmp::Reverse(*staked));
+
+            let mut remaining = value;
+            let mut surplus: Balance = 0;
+            for (dapp, staked) in stakes {
+                if remaining == 0 {
+                    break;
+                }
+                let amount = remaining.min(staked);
+                if amount > 0 {
+                    DappsStaking::unbond_and_unstake(dapp, amount)?;
+                    remaining -= amount;
+                    surplus += staked - self.staked_on(dapp) - amount;
+                }
+            }
+            if surplus > 0 {
+                self.surplus_unbonding.push(UnbondRequest {
+                    value: surplus,
+                    unlock_era,
+                });
+            }
+            Ok(())
+        }
+
+        /// Stakes back the surplus unbonding which can be withdrawn
+        fn restake_surplus(&mut self) -> Result<(), StakingPoolError> {
+            let current_era = DappsStaking::read_current_era();
+            let (matured, pending): (Vec<_>, Vec<_>) = self
+                .surplus_unbonding
+                .iter()
+                .copied()
+                .partition(|request| request.unlock_era <= current_era);
+            let value: Balance = matured.iter().map(|request| request.value).sum();
+            if value == 0 {
+                return Ok(());
+            }
+            match DappsStaking::withdraw_unbonded() {
+                Ok(()) | Err(DSError::NothingToWithdraw) => {}
+                Err(error) => return Err(error.into()),
+            }
+            // the most staked dApp stays above the pallet minimum
+            let dapp = self
+                .dapps
+                .iter()
+                .copied()
+                .max_by_key(|dapp| self.staked_on(*dapp))
+                .ok_or(StakingPoolError::NoDapps)?;
+            match DappsStaking::bond_and_stake(dapp, value) {
+                Ok(()) => self.surplus_unbonding = pending,
+                // too little to stake on its own, kept for a later compound
+                Err(DSError::InsufficientValue) => {}
+                Err(error) => return Err(error.into()),
+            }
+            Ok(())
+        }
+
+        fn mul_div(a: Balance, b: Balance, c: Balance) -> Balance {
+            multiply_by_rational_with_rounding(a, b, c, Rounding::Down).unwrap_or(0)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use dapps_staking_extension::mock::DappsStakingMock;
+        use ink::env::{test, DefaultEnvironment};
+
+        const ONE: Balance = 1_000_000_000_000_000_000;
+        const POOL: [u8; 32] = [0xC0; 32];
+        const DAPP_A: [u8; 32] = [0xDA; 32];
+        const DAPP_B: [u8; 32] = [0xDB; 32];
+        /// 8 eras on each dApp
+        const MAX_CLAIMS: u32 = 16;
+
+        fn setup(fee: Perbill) -> (StakingPool, DappsStakingMock) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_callee::<DefaultEnvironment>(AccountId::from(POOL));
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let mock = DappsStakingMock::register(AccountId::from(POOL));
+            mock.register_dapp(AccountId::from(DAPP_A));
+            mock.register_dapp(AccountId::from(DAPP_B));
+            mock.set_unbonding_period(2);
+            let pool =
+                StakingPool::new(vec![AccountId::from(DAPP_A), AccountId::from(DAPP_B)], fee)
+                    .unwrap();
+            (pool, mock)
+        }
+
+        fn deposit(
+            pool: &mut StakingPool,
+            account: AccountId,
+            value: Balance,
+        ) -> Result<Balance, StakingPoolError> {
+            test::set_caller::<DefaultEnvironment>(account);
+            test::set_value_transferred::<DefaultEnvironment>(value);
+            let result = pool.deposit();
+            test::set_value_transferred::<DefaultEnvironment>(0);
+            result
+        }
+
+        #[ink::test]
+        fn constructor_validates_config() {
+            test::set_callee::<DefaultEnvironment>(AccountId::from(POOL));
+            DappsStakingMock::register(AccountId::from(POOL));
+            assert_eq!(
+                StakingPool::new(Vec::new(), Perbill::zero()).err(),
+                Some(StakingPoolError::NoDapps)
+            );
+            assert_eq!(
+                StakingPool::new(vec![AccountId::from(DAPP_A)], Perbill::from_percent(31)).err(),
+                Some(StakingPoolError::FeeTooHigh)
+            );
+        }
+
+        #[ink::test]
+        fn deposits_are_spread_over_dapps() {
+            let (mut pool, mock) = setup(Perbill::zero());
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+
+            assert_eq!(deposit(&mut pool, accounts.bob, 100 * ONE), Ok(100 * ONE));
+            assert_eq!(deposit(&mut pool, accounts.charlie, 60 * ONE), Ok(60 * ONE));
+
+            let pool_account = AccountId::from(POOL);
+            assert_eq!(
+                mock.staked(pool_account, AccountId::from(DAPP_A)),
+                100 * ONE
+            );
+            assert_eq!(mock.staked(pool_account, AccountId::from(DAPP_B)), 60 * ONE);
+            assert_eq!(pool.total_pooled(), 160 * ONE);
+            assert_eq!(pool.shares_of(accounts.charlie), 60 * ONE);
+        }
+
+        #[ink::test]
+        fn compounding_takes_operator_fee() {
+            let (mut pool, mock) = setup(Perbill::from_percent(10));
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            deposit(&mut pool, accounts.bob, 100 * ONE).unwrap();
+
+            mock.set_era_rewards(0, 10 * ONE, 0);
+            mock.advance_era();
+            assert_eq!(pool.compound(MAX_CLAIMS), Ok(10 * ONE));
+            assert_eq!(pool.total_pooled(), 110 * ONE);
+            assert_eq!(pool.last_compounded_era(), 1);
+
+            // the operator owns exactly the 1 ONE fee
+            let fee_shares = pool.shares_of(accounts.alice);
+            assert_eq!(pool.shares_to_value(fee_shares), ONE - 1);
+            assert_eq!(pool.shares_to_value(100 * ONE), 109 * ONE);
+        }
+
+        #[ink::test]
+        fn deposit_waits_for_pending_rewards() {
+            let (mut pool, mock) = setup(Perbill::zero());
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            deposit(&mut pool, accounts.bob, 100 * ONE).unwrap();
+            mock.set_era_rewards(0, 10 * ONE, 0);
+            mock.advance_era();
+
+            assert_eq!(
+                deposit(&mut pool, accounts.charlie, 110 * ONE),
+                Err(StakingPoolError::CompoundPending)
+            );
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                pool.request_withdraw(ONE),
+                Err(StakingPoolError::CompoundPending)
+            );
+            assert_eq!(pool.compound(MAX_CLAIMS), Ok(10 * ONE));
+            assert_eq!(
+                deposit(&mut pool, accounts.charlie, 110 * ONE),
+                Ok(100 * ONE)
+            );
+            assert_eq!(pool.shares_to_value(100 * ONE), 110 * ONE);
+        }
+
+        #[ink::test]
+        fn eras_left_unclaimed_block_deposits() {
+            let (mut pool, mock) = setup(Perbill::zero());
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            deposit(&mut pool, accounts.bob, 100 * ONE).unwrap();
+            for era in 0..10 {
+                mock.set_era_rewards(era, ONE, 0);
+                mock.advance_era();
+            }
+
+            // one compound claims 8 of the 10 eras
+            assert_eq!(pool.compound(MAX_CLAIMS), Ok(8 * ONE));
+            assert_eq!(pool.last_compounded_era(), 8);
+            assert_eq!(
+                deposit(&mut pool, accounts.charlie, 110 * ONE),
+                Err(StakingPoolError::CompoundPending)
+            );
+            assert_eq!(pool.compound(MAX_CLAIMS), Ok(2 * ONE));
+            assert_eq!(pool.last_compounded_era(), 10);
+            assert_eq!(pool.total_pooled(), 110 * ONE);
+            assert_eq!(
+                deposit(&mut pool, accounts.charlie, 110 * ONE),
+                Ok(100 * ONE)
+            );
+        }
+
+        #[ink::test]
+        fn stake_unbonded_below_minimum_stays_pooled() {
+            let (mut pool, mock) = setup(Perbill::zero());
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            mock.set_minimum_stake(10 * ONE);
+            deposit(&mut pool, accounts.bob, 100 * ONE).unwrap();
+            deposit(&mut pool, accounts.charlie, 50 * ONE).unwrap();
+
+            // 5 ONE would be left on dApp A, so the pallet unstakes all of it
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(pool.request_withdraw(95 * ONE), Ok(95 * ONE));
+            let pool_account = AccountId::from(POOL);
+            assert_eq!(mock.staked(pool_account, AccountId::from(DAPP_A)), 0);
+            assert_eq!(pool.total_pooled(), 55 * ONE);
+            assert_eq!(pool.shares_to_value(50 * ONE), 50 * ONE);
+
+            mock.advance_era();
+            mock.advance_era();
+            assert_eq!(pool.compound(MAX_CLAIMS), Ok(0));
+            assert_eq!(mock.staked(pool_account, AccountId::from(DAPP_B)), 55 * ONE);
+            assert_eq!(pool.total_pooled(), 55 * ONE);
+            test::set_account_balance::<DefaultEnvironment>(pool_account, 95 * ONE);
+            assert_eq!(pool.withdraw(), Ok(95 * ONE));
+        }
+
+        #[ink::test]
+        fn redeem_waits_for_unbonding_period() {
+            let (mut pool, mock) = setup(Perbill::zero());
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            deposit(&mut pool, accounts.bob, 100 * ONE).unwrap();
+            deposit(&mut pool, accounts.charlie, 50 * ONE).unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                pool.request_withdraw(101 * ONE),
+                Err(StakingPoolError::InsufficientShares)
+            );
+            assert_eq!(pool.request_withdraw(80 * ONE), Ok(80 * ONE));
+            assert_eq!(pool.total_pooled(), 70 * ONE);
+            assert_eq!(pool.withdraw(), Err(StakingPoolError::NothingToWithdraw));
+
+            mock.advance_era();
+            mock.advance_era();
+            test::set_account_balance::<DefaultEnvironment>(AccountId::from(POOL), 80 * ONE);
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap();
+            assert_eq!(pool.withdraw(), Ok(80 * ONE));
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap(),
+                balance + 80 * ONE
+            );
+            assert_eq!(pool.total_unbonding(), 0);
+        }
+
+        #[ink::test]
+        fn operator_calls_are_owner_only() {
+            let (mut pool, _) = setup(Perbill::zero());
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(
+                pool.set_fee(Perbill::from_percent(5)),
+                Err(StakingPoolError::NotOwner)
+            );
+            assert_eq!(pool.add_dapp(accounts.eve), Err(StakingPoolError::NotOwner));
+            assert_eq!(
+                pool.transfer_ownership(accounts.bob),
+                Err(StakingPoolError::NotOwner)
+            );
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(
+                pool.set_fee(Perbill::from_percent(50)),
+                Err(StakingPoolError::FeeTooHigh)
+            );
+            assert_eq!(pool.transfer_ownership(accounts.bob), Ok(()));
+            assert_eq!(pool.owner(), accounts.bob);
+        }
+
+        #[ink::test]
+        fn removed_dapp_stake_moves_to_remaining_dapp() {
+            let (mut pool, mock) = setup(Perbill::zero());
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            deposit(&mut pool, accounts.bob, 100 * ONE).unwrap();
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(pool.remove_dapp(AccountId::from(DAPP_A)), Ok(()));
+            assert_eq!(pool.dapps(), vec![AccountId::from(DAPP_B)]);
+            assert_eq!(
+                mock.staked(AccountId::from(POOL), AccountId::from(DAPP_B)),
+                100 * ONE
+            );
+            assert_eq!(
+                pool.remove_dapp(AccountId::from(DAPP_B)),
+                Err(StakingPoolError::NoDapps)
+            );
+        }
+
+        #[ink::test]
+        fn removed_dapp_stake_below_minimum_is_unbonded() {
+            let (mut pool, mock) = setup(Perbill::zero());
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            deposit(&mut pool, accounts.bob, 5 * ONE).unwrap();
+            mock.set_minimum_stake(10 * ONE);
+
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            assert_eq!(pool.remove_dapp(AccountId::from(DAPP_A)), Ok(()));
+            let pool_account = AccountId::from(POOL);
+            assert_eq!(mock.staked(pool_account, AccountId::from(DAPP_A)), 0);
+            assert_eq!(mock.staked(pool_account, AccountId::from(DAPP_B)), 0);
+            assert_eq!(pool.total_pooled(), 5 * ONE);
+            assert_eq!(pool.shares_to_value(5 * ONE), 5 * ONE);
+        }
+    }
+}
@@ -0,0 +1,486 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod staking_rebalancer {
+    use dapps_staking_extension::*;
+    use ink::prelude::vec::Vec;
+    use sp_arithmetic::helpers_128bit::multiply_by_rational_with_rounding;
+    use sp_arithmetic::Rounding;
+
+    /// Maximum number of dApps the contract stakes on
+    pub const MAX_DAPPS: u32 = 16;
+
+    #[ink(event)]
+    pub struct Rebalanced {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        value: Balance,
+    }
+
+    #[ink(event)]
+    pub struct TransferSkipped {
+        #[ink(topic)]
+        from: AccountId,
+        #[ink(topic)]
+        to: AccountId,
+        value: Balance,
+        error: DSError,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum RebalancerError {
+        /// Error from the dapps-staking pallet
+        DappsStaking(DSError),
+        NotOwner,
+        /// At least one dApp with a non-zero weight is required
+        NoTargets,
+        TooManyDapps,
+        DuplicateDapp,
+        ZeroAmount,
+        /// Native transfer to the owner failed
+        TransferFailed,
+    }
+
+    impl From<DSError> for RebalancerError {
+        fn from(error: DSError) -> Self {
+            RebalancerError::DappsStaking(error)
+        }
+    }
+
+    /// A nomination transfer needed to reach the target weights
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub struct Move {
+        pub from: AccountId,
+        pub to: AccountId,
+        pub value: Balance,
+    }
+
+    /// Keeps the stake of the contract spread over several dApps by target weight.
+    ///
+    /// `rebalance` moves stake with `nomination_transfer`, so rebalancing never goes
+    /// through the unbonding period. Drifts smaller than `min_transfer` are left alone.
+    #[ink(storage)]
+    pub struct StakingRebalancer {
+        owner: AccountId,
+        /// Target weight per dApp, dApps left with a zero weight are emptied
+        targets: Vec<(AccountId, u32)>,
+        min_transfer: Balance,
+    }
+
+    impl StakingRebalancer {
+        #[ink(constructor)]
+        pub fn new(
+            targets: Vec<(AccountId, u32)>,
+            min_transfer: Balance,
+        ) -> Result<Self, RebalancerError> {
+            Self::validate(&targets)?;
+            Ok(Self {
+                owner: Self::env().caller(),
+                targets,
+                min_transfer,
+            })
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn targets(&self) -> Vec<(AccountId, u32)> {
+            self.targets.clone()
+        }
+
+        #[ink(message)]
+        pub fn min_transfer(&self) -> Balance {
+            self.min_transfer
+        }
+
+        /// Stake of the contract on every tracked dApp
+        #[ink(message)]
+        pub fn stakes(&self) -> Vec<(AccountId, Balance)> {
+            let contract = self.env().account_id();
+            self.targets
+                .iter()
+                .map(|(dapp, _)| {
+                    (
+                        *dapp,
+                        DappsStaking::read_staked_amount_on_contract(contract, *dapp),
+                    )
+                })
+                .collect()
+        }
+
+        /// Nomination transfers `rebalance` would issue with the current stakes
+        #[ink(message)]
+        pub fn plan(&self) -> Vec<Move> {
+            let stakes = self.stakes();
+            let total: Balance = stakes.iter().map(|(_, staked)| staked).sum();
+            let total_weight: u128 = self.targets.iter().map(|(_, w)| *w as u128).sum();
+
+            let mut surpluses = Vec::new();
+            let mut deficits = Vec::new();
+            for ((dapp, staked), (_, weight)) in stakes.into_iter().zip(self.targets.iter()) {
+                let target = multiply_by_rational_with_rounding(
+                    total,
+                    *weight as u128,
+                    total_weight,
+                    Rounding::Down,
+                )
+                .unwrap_or(0);
+                if staked > target {
+                    surpluses.push((dapp, staked - target));
+                } else if target > staked {
+                    deficits.push((dapp, target - staked));
+                }
+            }
+            surpluses.sort_by_key(|(_, value)| core::cmp::Reverse(*value));
+            deficits.sort_by_key(|(_, value)| core::cmp::Reverse(*value));
+
+            // pairing the largest surplus with the largest deficit settles at least
+            // one of them per move, so there are fewer moves than dApps
+            let mut moves = Vec::new();
+            let (mut s, mut d) = (0, 0);
+            while s < surpluses.len() && d < deficits.len() {
+                let value = surpluses[s].1.min(deficits[d].1);
+                if value >= self.min_transfer && value > 0 {
+                    moves.push(Move {
+                        from: surpluses[s].0,
+                        to: deficits[d].0,
+                        value,
+                    });
+                }
+                surpluses[s].1 -= value;
+                deficits[d].1 -= value;
+                if surpluses[s].1 == 0 {
+                    s += 1;
+                }
+                if deficits[d].1 == 0 {
+                    d += 1;
+                }
+            }
+            moves
+        }
+
+        /// Issues the planned nomination transfers. Transfers the pallet rejects for
+        /// being too small, to a full dApp or to the same dApp are skipped.
+        /// Returns the number of executed transfers.
+        #[ink(message)]
+        pub fn rebalance(&mut self) -> Result<u32, RebalancerError> {
+            let mut full: Vec<AccountId> = Vec::new();
+            let mut executed = 0;
+            for Move { from, to, value } in self.plan() {
+                let result = if full.contains(&to) {
+                    Err(DSError::MaxNumberOfStakersExceeded)
+                } else {
+                    DappsStaking::nomination_transfer(from, to, value)
+                };
+                match result {
+                    Ok(()) => {
+                        executed += 1;
+                        self.env().emit_event(Rebalanced { from, to, value });
+                    }
+                    Err(
+                        error @ (DSError::NominationTransferToSameContract
+                        | DSError::InsufficientValue
+                        | DSError::MaxNumberOfStakersExceeded),
+                    ) => {
+                        if error == DSError::MaxNumberOfStakersExceeded && !full.contains(&to) {
+                            full.push(to);
+                        }
+                        self.env().emit_event(TransferSkipped {
+                            from,
+                            to,
+                            value,
+                            error,
+                        });
+                    }
+                    Err(error) => return Err(error.into()),
+                }
+            }
+            Ok(executed)
+        }
+
+        /// Stakes the transferred value on the most underweight dApp
+        #[ink(message, payable)]
+        pub fn stake(&mut self) -> Result<(), RebalancerError> {
+            self.ensure_owner()?;
+            let value = self.env().transferred_value();
+            if value == 0 {
+                return Err(RebalancerError::ZeroAmount);
+            }
+            let stakes = self.stakes();
+            let total: Balance = stakes.iter().map(|(_, staked)| staked).sum::<Balance>() + value;
+            let total_weight: u128 = self.targets.iter().map(|(_, w)| *w as u128).sum();
+            let (dapp, _) = stakes
+                .into_iter()
+                .zip(self.targets.iter())
+                .map(|((dapp, staked), (_, weight))| {
+                    let target = multiply_by_rational_with_rounding(
+                        total,
+                        *weight as u128,
+                        total_weight,
+                        Rounding::Down,
+                    )
+                    .unwrap_or(0);
+                    (dapp, target.saturating_sub(staked))
+                })
+                .max_by_key(|(_, deficit)| *deficit)
+                .ok_or(RebalancerError::NoTargets)?;
+            DappsStaking::bond_and_stake(dapp, value)?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn unbond_and_unstake(
+            &mut self,
+            dapp: AccountId,
+            value: Balance,
+        ) -> Result<(), RebalancerError> {
+            self.ensure_owner()?;
+            DappsStaking::unbond_and_unstake(dapp, value)?;
+            Ok(())
+        }
+
+        /// Withdraws unbonded funds and sends them to the owner. The contract
+        /// balance also holds the stake still locked by the pallet and the
+        /// existential deposit, which stay in the contract.
+        #[ink(message)]
+        pub fn withdraw_unbonded(&mut self) -> Result<Balance, RebalancerError> {
+            self.ensure_owner()?;
+            let contract = self.env().account_id();
+            let locked_before = DappsStaking::read_staked_amount(contract);
+            DappsStaking::withdraw_unbonded()?;
+            let locked = DappsStaking::read_staked_amount(contract);
+            let transferable = self
+                .env()
+                .balance()
+                .saturating_sub(locked + self.env().minimum_balance());
+            let value = locked_before.saturating_sub(locked).min(transferable);
+            self.env()
+                .transfer(self.owner, value)
+                .map_err(|_| RebalancerError::TransferFailed)?;
+            Ok(value)
+        }
+
+        /// Replaces the target weights. dApps that still hold stake must stay
+        /// listed, with a zero weight, until `rebalance` emptied them.
+        #[ink(message)]
+        pub fn set_targets(
+            &mut self,
+            targets: Vec<(AccountId, u32)>,
+        ) -> Result<(), RebalancerError> {
+            self.ensure_owner()?;
+            Self::validate(&targets)?;
+            let contract = self.env().account_id();
+            let mut targets = targets;
+            for (dapp, _) in self.targets.iter() {
+                let staked = DappsStaking::read_staked_amount_on_contract(contract, *dapp);
+                if staked > 0 && !targets.iter().any(|(d, _)| d == dapp) {
+                    targets.push((*dapp, 0));
+                }
+            }
+            if targets.len() as u32 > MAX_DAPPS {
+                return Err(RebalancerError::TooManyDapps);
+            }
+            self.targets = targets;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn set_min_transfer(&mut self, min_transfer: Balance) -> Result<(), RebalancerError> {
+            self.ensure_owner()?;
+            self.min_transfer = min_transfer;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), RebalancerError> {
+            self.ensure_owner()?;
+            self.owner = new_owner;
+            Ok(())
+        }
+
+        fn ensure_owner(&self) -> Result<(), RebalancerError> {
+            if self.env().caller() != self.owner {
+                return Err(RebalancerError::NotOwner);
+            }
+            Ok(())
+        }
+
+        fn validate(targets: &[(AccountId, u32)]) -> Result<(), RebalancerError> {
+            if targets.len() as u32 > MAX_DAPPS {
+                return Err(RebalancerError::TooManyDapps);
+            }
+            if targets.iter().all(|(_, weight)| *weight == 0) {
+                return Err(RebalancerError::NoTargets);
+            }
+            for (i, (dapp, _)) in targets.iter().enumerate() {
+                if targets[i + 1..].iter().any(|(d, _)| d == dapp) {
+                    return Err(RebalancerError::DuplicateDapp);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use dapps_staking_extension::mock::DappsStakingMock;
+        use ink::env::{test, DefaultEnvironment};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+
+        fn dapp(id: u8) -> AccountId {
+            AccountId::from([id; 32])
+        }
+
+        fn setup(targets: Vec<(AccountId, u32)>) -> (StakingRebalancer, DappsStakingMock) {
+            test::set_callee::<DefaultEnvironment>(AccountId::from(CONTRACT));
+            let mock = DappsStakingMock::register(AccountId::from(CONTRACT));
+            for (dapp, _) in targets.iter() {
+                mock.register_dapp(*dapp);
+            }
+            (StakingRebalancer::new(targets, 0).unwrap(), mock)
+        }
+
+        fn staked(mock: &DappsStakingMock, id: u8) -> Balance {
+            mock.staked(AccountId::from(CONTRACT), dapp(id))
+        }
+
+        #[ink::test]
+        fn plan_uses_fewest_transfers() {
+            let (mut contract, mock) = setup(vec![(dapp(1), 1), (dapp(2), 1), (dapp(3), 2)]);
+            mock.stake_as(AccountId::from(CONTRACT), dapp(1), 400)
+                .unwrap();
+
+            assert_eq!(
+                contract.plan(),
+                vec![
+                    Move {
+                        from: dapp(1),
+                        to: dapp(3),
+                        value: 200
+                    },
+                    Move {
+                        from: dapp(1),
+                        to: dapp(2),
+                        value: 100
+                    },
+                ]
+            );
+            assert_eq!(contract.rebalance(), Ok(2));
+            assert_eq!(
+                (staked(&mock, 1), staked(&mock, 2), staked(&mock, 3)),
+                (100, 100, 200)
+            );
+            assert!(contract.plan().is_empty());
+        }
+
+        #[ink::test]
+        fn small_drift_is_ignored() {
+            let (mut contract, mock) = setup(vec![(dapp(1), 1), (dapp(2), 1)]);
+            mock.stake_as(AccountId::from(CONTRACT), dapp(1), 110)
+                .unwrap();
+            mock.stake_as(AccountId::from(CONTRACT), dapp(2), 90)
+                .unwrap();
+
+            contract.set_min_transfer(20).unwrap();
+            assert!(contract.plan().is_empty());
+            contract.set_min_transfer(10).unwrap();
+            assert_eq!(contract.rebalance(), Ok(1));
+        }
+
+        #[ink::test]
+        fn rejected_transfers_are_skipped() {
+            let (mut contract, mock) = setup(vec![(dapp(1), 2), (dapp(2), 1), (dapp(3), 1)]);
+            mock.stake_as(AccountId::from(CONTRACT), dapp(1), 400)
+                .unwrap();
+            // dApp 2 is full and a move of 100 is below the minimum stake
+            mock.set_max_stakers(1);
+            mock.stake_as(dapp(9), dapp(2), 1_000).unwrap();
+            mock.set_minimum_stake(150);
+
+            assert_eq!(contract.rebalance(), Ok(0));
+            let skipped = test::recorded_events().count();
+            assert_eq!(skipped, 2);
+            assert_eq!(staked(&mock, 1), 400);
+        }
+
+        #[ink::test]
+        fn removed_dapp_is_emptied() {
+            let (mut contract, mock) = setup(vec![(dapp(1), 1), (dapp(2), 1)]);
+            mock.stake_as(AccountId::from(CONTRACT), dapp(1), 100)
+                .unwrap();
+            mock.stake_as(AccountId::from(CONTRACT), dapp(2), 100)
+                .unwrap();
+
+            contract.set_targets(vec![(dapp(2), 1)]).unwrap();
+            assert_eq!(contract.targets(), vec![(dapp(2), 1), (dapp(1), 0)]);
+            assert_eq!(contract.rebalance(), Ok(1));
+            assert_eq!((staked(&mock, 1), staked(&mock, 2)), (0, 200));
+        }
+
+        #[ink::test]
+        fn stake_goes_to_most_underweight_dapp() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let (mut contract, mock) = setup(vec![(dapp(1), 1), (dapp(2), 3)]);
+
+            test::set_value_transferred::<DefaultEnvironment>(100);
+            assert_eq!(contract.stake(), Ok(()));
+            assert_eq!(staked(&mock, 2), 100);
+
+            test::set_caller::<DefaultEnvironment>(accounts.bob);
+            assert_eq!(contract.stake(), Err(RebalancerError::NotOwner));
+        }
+
+        #[ink::test]
+        fn withdraw_sends_only_unlocked_funds() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            test::set_caller::<DefaultEnvironment>(accounts.alice);
+            let (mut contract, mock) = setup(vec![(dapp(1), 1)]);
+            mock.set_unbonding_period(2);
+            mock.stake_as(AccountId::from(CONTRACT), dapp(1), 400)
+                .unwrap();
+            contract.unbond_and_unstake(dapp(1), 100).unwrap();
+            mock.advance_era();
+            mock.advance_era();
+
+            // the contract holds its whole stake and the existential deposit
+            let minimum_balance = ink::env::minimum_balance::<DefaultEnvironment>();
+            test::set_account_balance::<DefaultEnvironment>(
+                AccountId::from(CONTRACT),
+                400 + minimum_balance,
+            );
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.alice).unwrap();
+            assert_eq!(contract.withdraw_unbonded(), Ok(100));
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.alice),
+                Ok(balance + 100)
+            );
+            assert_eq!(
+                contract.withdraw_unbonded(),
+                Err(RebalancerError::DappsStaking(DSError::NothingToWithdraw))
+            );
+        }
+
+        #[ink::test]
+        fn invalid_targets_are_rejected() {
+            test::set_callee::<DefaultEnvironment>(AccountId::from(CONTRACT));
+            assert_eq!(
+                StakingRebalancer::new(vec![(dapp(1), 0)], 0).err(),
+                Some(RebalancerError::NoTargets)
+            );
+            assert_eq!(
+                StakingRebalancer::new(vec![(dapp(1), 1), (dapp(1), 2)], 0).err(),
+                Some(RebalancerError::DuplicateDapp)
+            );
+        }
+    }
+}
@@ -0,0 +1,576 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod subscriptions {
+    use assets_extension::{AssetsError, AssetsExtension};
+    use ink::env::call::Selector;
+    use ink::storage::Mapping;
+    use scheduler_extension::{
+        ContractCallInput, SchedulerError, SchedulerExtension, CALL_GAS_LIMIT, CALL_MAX_WEIGHT,
+    };
+
+    #[ink(event)]
+    pub struct PlanCreated {
+        #[ink(topic)]
+        plan_id: u64,
+        #[ink(topic)]
+        merchant: AccountId,
+        plan: Plan,
+    }
+
+    #[ink(event)]
+    pub struct Subscribed {
+        #[ink(topic)]
+        subscription_id: u64,
+        #[ink(topic)]
+        plan_id: u64,
+        #[ink(topic)]
+        subscriber: AccountId,
+        ends_at: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct Charged {
+        #[ink(topic)]
+        subscription_id: u64,
+        amount: Balance,
+        paid_until: BlockNumber,
+    }
+
+    /// A charge failed because the subscriber revoked the allowance or ran
+    /// out of funds
+    #[ink(event)]
+    pub struct Suspended {
+        #[ink(topic)]
+        subscription_id: u64,
+        reason: AssetsError,
+    }
+
+    #[ink(event)]
+    pub struct Resumed {
+        #[ink(topic)]
+        subscription_id: u64,
+        paid_until: BlockNumber,
+    }
+
+    #[ink(event)]
+    pub struct Cancelled {
+        #[ink(topic)]
+        subscription_id: u64,
+        cancelled_by: AccountId,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum SubscriptionError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        /// Error from the scheduler pallet
+        Scheduler(SchedulerError),
+        /// Amount, period or number of periods is zero
+        ZeroAmount,
+        Overflow,
+        PlanNotFound,
+        SubscriptionNotFound,
+        NotSubscriber,
+        /// Caller is neither the subscriber nor the merchant
+        NotParty,
+        /// The subscription is suspended or cancelled
+        NotActive,
+        NotSuspended,
+        /// The current period is already paid
+        NotDue,
+        /// All the periods of the subscription are over
+        Ended,
+    }
+
+    impl From<AssetsError> for SubscriptionError {
+        fn from(error: AssetsError) -> Self {
+            SubscriptionError::Assets(error)
+        }
+    }
+
+    impl From<SchedulerError> for SubscriptionError {
+        fn from(error: SchedulerError) -> Self {
+            SubscriptionError::Scheduler(error)
+        }
+    }
+
+    /// `amount` of `asset_id` paid to `merchant` every `period` blocks
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Plan {
+        pub merchant: AccountId,
+        pub asset_id: u128,
+        pub amount: Balance,
+        pub period: BlockNumber,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub enum Status {
+        Active,
+        /// A charge failed, until the subscriber resumes
+        Suspended,
+        Cancelled,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Subscription {
+        pub plan_id: u64,
+        pub subscriber: AccountId,
+        pub status: Status,
+        /// Block of the first period, charges are due every period from there
+        pub started_at: BlockNumber,
+        /// Block the last period ends
+        pub ends_at: BlockNumber,
+        /// Block the paid periods end, when the next charge is due
+        pub paid_until: BlockNumber,
+    }
+
+    /// Recurring payments in pallet assets.
+    ///
+    /// Merchants define plans and users subscribe for a number of periods
+    /// after approving the contract to transfer their assets. The first period
+    /// is charged on subscription and the following ones by a periodic
+    /// scheduled `charge`, paid by the subscriber. A charge failing for a
+    /// missing allowance or balance suspends the subscription, and the
+    /// subscriber resumes it by paying the current period.
+    #[ink(storage)]
+    pub struct Subscriptions {
+        plans: Mapping<u64, Plan>,
+        next_plan_id: u64,
+        subscriptions: Mapping<u64, Subscription>,
+        next_subscription_id: u64,
+    }
+
+    impl Subscriptions {
+        #[ink(constructor)]
+        pub fn new() -> Self {
+            Self {
+                plans: Mapping::default(),
+                next_plan_id: 0,
+                subscriptions: Mapping::default(),
+                next_subscription_id: 0,
+            }
+        }
+
+        #[ink(message)]
+        pub fn plan(&self, plan_id: u64) -> Option<Plan> {
+            self.plans.get(plan_id)
+        }
+
+        #[ink(message)]
+        pub fn subscription(&self, subscription_id: u64) -> Option<Subscription> {
+            self.subscriptions.get(subscription_id)
+        }
+
+        /// Creates a plan paid to the caller
+        #[ink(message)]
+        pub fn create_plan(
+            &mut self,
+            asset_id: u128,
+            amount: Balance,
+            period: BlockNumber,
+        ) -> Result<u64, SubscriptionError> {
+            if amount == 0 || period == 0 {
+                return Err(SubscriptionError::ZeroAmount);
+            }
+            let plan = Plan {
+                merchant: self.env().caller(),
+                asset_id,
+                amount,
+                period,
+            };
+            let plan_id = self.next_plan_id;
+            self.next_plan_id += 1;
+            self.plans.insert(plan_id, &plan);
+            self.env().emit_event(PlanCreated {
+                plan_id,
+                merchant: plan.merchant,
+                plan,
+            });
+            Ok(plan_id)
+        }
+
+        /// Subscribes the caller to `plan_id` for `periods` periods, charging
+        /// the first one. The caller must have approved the contract to
+        /// transfer the plan amount each period.
+        #[ink(message)]
+        pub fn subscribe(&mut self, plan_id: u64, periods: u32) -> Result<u64, SubscriptionError> {
+            let plan = self
+                .plans
+                .get(plan_id)
+                .ok_or(SubscriptionError::PlanNotFound)?;
+            if periods == 0 {
+                return Err(SubscriptionError::ZeroAmount);
+            }
+            let now = self.env().block_number();
+            let ends_at = plan
+                .period
+                .checked_mul(periods)
+                .and_then(|duration| now.checked_add(duration))
+                .ok_or(SubscriptionError::Overflow)?;
+            let subscriber = self.env().caller();
+            Self::pull(&plan, subscriber)?;
+
+            let subscription_id = self.next_subscription_id;
+            self.next_subscription_id += 1;
+            if periods > 1 {
+                SchedulerExtension::schedule(
+                    scheduler_extension::Origin::Caller,
+                    now + plan.period,
+                    Some((plan.period, periods - 1)),
+                    0,
+                    self.charge_call(subscription_id),
+                )?;
+            }
+            self.subscriptions.insert(
+                subscription_id,
+                &Subscription {
+                    plan_id,
+                    subscriber,
+                    status: Status::Active,
+                    started_at: now,
+                    ends_at,
+                    paid_until: now + plan.period,
+                },
+            );
+            self.env().emit_event(Subscribed {
+                subscription_id,
+                plan_id,
+                subscriber,
+                ends_at,
+            });
+            self.env().emit_event(Charged {
+                subscription_id,
+                amount: plan.amount,
+                paid_until: now + plan.period,
+            });
+            Ok(subscription_id)
+        }
+
+        /// Charges the next period once it is due. Called by the scheduler, but
+        /// anyone can call it.
+        ///
+        /// Returns `Ok(false)` when the subscriber revoked the allowance or ran
+        /// out of funds: the subscription is then suspended, and the call
+        /// succeeds so the suspension is kept.
+        #[ink(message)]
+        pub fn charge(&mut self, subscription_id: u64) -> Result<bool, SubscriptionError> {
+            let mut subscription = self.get(subscription_id)?;
+            if subscription.status != Status::Active {
+                return Err(SubscriptionError::NotActive);
+            }
+            if subscription.paid_until >= subscription.ends_at {
+                return Err(SubscriptionError::Ended);
+            }
+            if self.env().block_number() < subscription.paid_until {
+                return Err(SubscriptionError::NotDue);
+            }
+            let plan = self
+                .plans
+                .get(subscription.plan_id)
+                .ok_or(SubscriptionError::PlanNotFound)?;
+            match Self::pull(&plan, subscription.subscriber) {
+                Ok(()) => {
+                    subscription.paid_until += plan.period;
+                    self.subscriptions.insert(subscription_id, &subscription);
+                    self.env().emit_event(Charged {
+                        subscription_id,
+                        amount: plan.amount,
+                        paid_until: subscription.paid_until,
+                    });
+                    Ok(true)
+                }
+                Err(reason @ (AssetsError::Unapproved | AssetsError::BalanceLow)) => {
+                    subscription.status = Status::Suspended;
+                    self.subscriptions.insert(subscription_id, &subscription);
+                    self.env().emit_event(Suspended {
+                        subscription_id,
+                        reason,
+                    });
+                    Ok(false)
+                }
+                Err(error) => Err(error.into()),
+            }
+        }
+
+        /// Reactivates a suspended subscription of the caller by paying the
+        /// current period. Periods missed while suspended are not charged, and
+        /// the scheduled charges carry on.
+        #[ink(message)]
+        pub fn resume(&mut self, subscription_id: u64) -> Result<(), SubscriptionError> {
+            let mut subscription = self.get(subscription_id)?;
+            if self.env().caller() != subscription.subscriber {
+                return Err(SubscriptionError::NotSubscriber);
+            }
+            if subscription.status != Status::Suspended {
+                return Err(SubscriptionError::NotSuspended);
+            }
+            let plan = self
+                .plans
+                .get(subscription.plan_id)
+                .ok_or(SubscriptionError::PlanNotFound)?;
+            let elapsed = self.env().block_number() - subscription.started_at;
+            let period_start = subscription.started_at + elapsed / plan.period * plan.period;
+            if period_start >= subscription.ends_at {
+                return Err(SubscriptionError::Ended);
+            }
+            Self::pull(&plan, subscription.subscriber)?;
+            subscription.status = Status::Active;
+            subscription.paid_until = period_start + plan.period;
+            self.subscriptions.insert(subscription_id, &subscription);
+            self.env().emit_event(Resumed {
+                subscription_id,
+                paid_until: subscription.paid_until,
+            });
+            self.env().emit_event(Charged {
+                subscription_id,
+                amount: plan.amount,
+                paid_until: subscription.paid_until,
+            });
+            Ok(())
+        }
+
+        /// Stops the charges, by the subscriber or the merchant. The periods
+        /// already paid are not refunded.
+        ///
+        /// The scheduled `charge` calls are not cancelled, the scheduler giving
+        /// no address to cancel them with: the remaining runs are still
+        /// dispatched, paid by the subscriber, and fail with `NotActive`.
+        #[ink(message)]
+        pub fn cancel(&mut self, subscription_id: u64) -> Result<(), SubscriptionError> {
+            let mut subscription = self.get(subscription_id)?;
+            let caller = self.env().caller();
+            let merchant = self
+                .plans
+                .get(subscription.plan_id)
+                .map(|plan| plan.merchant);
+            if caller != subscription.subscriber && Some(caller) != merchant {
+                return Err(SubscriptionError::NotParty);
+            }
+            if subscription.status == Status::Cancelled {
+                return Err(SubscriptionError::NotActive);
+            }
+            subscription.status = Status::Cancelled;
+            self.subscriptions.insert(subscription_id, &subscription);
+            self.env().emit_event(Cancelled {
+                subscription_id,
+                cancelled_by: caller,
+            });
+            Ok(())
+        }
+
+        fn get(&self, subscription_id: u64) -> Result<Subscription, SubscriptionError> {
+            self.subscriptions
+                .get(subscription_id)
+                .ok_or(SubscriptionError::SubscriptionNotFound)
+        }
+
+        fn pull(plan: &Plan, subscriber: AccountId) -> Result<(), AssetsError> {
+            AssetsExtension::transfer_approved(
+                assets_extension::Origin::Address,
+                plan.asset_id,
+                subscriber,
+                plan.merchant,
+                plan.amount,
+            )
+        }
+
+        fn charge_call(&self, subscription_id: u64) -> ContractCallInput {
+            let mut data = Selector::new(ink::selector_bytes!("charge"))
+                .to_bytes()
+                .to_vec();
+            scale::Encode::encode_to(&subscription_id, &mut data);
+            ContractCallInput {
+                dest: self.env().account_id(),
+                data,
+                gas_limit: CALL_GAS_LIMIT,
+                storage_deposit_limit: None,
+                value: 0,
+                max_weight: CALL_MAX_WEIGHT,
+            }
+        }
+    }
+
+    impl Default for Subscriptions {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink::env::{test, DefaultEnvironment};
+        use scheduler_extension::mock::Mocks;
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const ASSET: u128 = 1;
+
+        /// Plan of 100 every 10 blocks to bob, alice holding 1_000
+        fn setup() -> (
+            Subscriptions,
+            Mocks,
+            test::DefaultAccounts<DefaultEnvironment>,
+            u64,
+        ) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mocks = Mocks::register(AccountId::from(CONTRACT));
+            mocks.assets.create(ASSET, accounts.alice, 1);
+            mocks.assets.mint(ASSET, accounts.alice, 1_000);
+            let mut subscriptions = Subscriptions::new();
+            mocks.set_caller(accounts.bob);
+            let plan_id = subscriptions.create_plan(ASSET, 100, 10).unwrap();
+            mocks.set_caller(accounts.alice);
+            (subscriptions, mocks, accounts, plan_id)
+        }
+
+        fn approve(mocks: &Mocks, owner: AccountId, amount: Balance) {
+            mocks
+                .assets
+                .approve(ASSET, owner, AccountId::from(CONTRACT), amount);
+        }
+
+        #[ink::test]
+        fn scheduler_charges_each_period() {
+            let (mut subscriptions, mocks, accounts, plan_id) = setup();
+            approve(&mocks, accounts.alice, 1_000);
+            let subscription_id = subscriptions.subscribe(plan_id, 3).unwrap();
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.bob), 100);
+            assert_eq!(
+                subscriptions.charge(subscription_id),
+                Err(SubscriptionError::NotDue)
+            );
+
+            for block in [10, 20] {
+                let due = mocks.scheduler.run_to_block(block);
+                assert_eq!(due.len(), 1);
+                assert_eq!(due[0].1.origin, accounts.alice);
+                assert_eq!(due[0].1.call, subscriptions.charge_call(subscription_id));
+                // dispatched by the scheduler
+                assert_eq!(subscriptions.charge(subscription_id), Ok(true));
+            }
+            assert!(mocks.scheduler.run_to_block(100).is_empty());
+            assert_eq!(
+                subscriptions.charge(subscription_id),
+                Err(SubscriptionError::Ended)
+            );
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.bob), 300);
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.alice), 700);
+        }
+
+        #[ink::test]
+        fn failed_charge_suspends_until_resumed() {
+            let (mut subscriptions, mocks, accounts, plan_id) = setup();
+            approve(&mocks, accounts.alice, 150);
+            let subscription_id = subscriptions.subscribe(plan_id, 5).unwrap();
+
+            mocks.scheduler.run_to_block(10);
+            let events = test::recorded_events().count();
+            assert_eq!(subscriptions.charge(subscription_id), Ok(false));
+            assert_eq!(test::recorded_events().count(), events + 1);
+            let subscription = subscriptions.subscription(subscription_id).unwrap();
+            assert_eq!(subscription.status, Status::Suspended);
+            assert_eq!(subscription.paid_until, 10);
+
+            mocks.scheduler.run_to_block(20);
+            assert_eq!(
+                subscriptions.charge(subscription_id),
+                Err(SubscriptionError::NotActive)
+            );
+
+            // the period from 20 is charged, the one from 10 is skipped
+            mocks.scheduler.run_to_block(25);
+            approve(&mocks, accounts.alice, 1_000);
+            assert_eq!(subscriptions.resume(subscription_id), Ok(()));
+            assert_eq!(
+                subscriptions
+                    .subscription(subscription_id)
+                    .unwrap()
+                    .paid_until,
+                30
+            );
+            mocks.scheduler.run_to_block(30);
+            assert_eq!(subscriptions.charge(subscription_id), Ok(true));
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.bob), 300);
+        }
+
+        #[ink::test]
+        fn recurring_charges_need_caller_origin() {
+            let (mut subscriptions, mocks, accounts, plan_id) = setup();
+            approve(&mocks, accounts.alice, 1_000);
+            mocks.scheduler.reject_caller_origin();
+
+            assert_eq!(
+                subscriptions.subscribe(plan_id, 3),
+                Err(SubscriptionError::Scheduler(
+                    SchedulerError::OriginCannotBeCaller
+                ))
+            );
+            // a single period needs no scheduled charge
+            assert!(subscriptions.subscribe(plan_id, 1).is_ok());
+        }
+
+        #[ink::test]
+        fn low_balance_suspends() {
+            let (mut subscriptions, mocks, accounts, plan_id) = setup();
+            mocks.assets.mint(ASSET, accounts.charlie, 150);
+            approve(&mocks, accounts.charlie, 1_000);
+            mocks.set_caller(accounts.charlie);
+            let subscription_id = subscriptions.subscribe(plan_id, 2).unwrap();
+
+            mocks.scheduler.run_to_block(10);
+            assert_eq!(subscriptions.charge(subscription_id), Ok(false));
+            assert_eq!(
+                subscriptions.resume(subscription_id),
+                Err(SubscriptionError::Assets(AssetsError::BalanceLow))
+            );
+            mocks.scheduler.run_to_block(20);
+            assert_eq!(
+                subscriptions.resume(subscription_id),
+                Err(SubscriptionError::Ended)
+            );
+        }
+
+        #[ink::test]
+        fn cancel_stops_charges() {
+            let (mut subscriptions, mocks, accounts, plan_id) = setup();
+            approve(&mocks, accounts.alice, 1_000);
+            assert_eq!(
+                subscriptions.subscribe(plan_id + 1, 2),
+                Err(SubscriptionError::PlanNotFound)
+            );
+            let subscription_id = subscriptions.subscribe(plan_id, 3).unwrap();
+
+            mocks.set_caller(accounts.eve);
+            assert_eq!(
+                subscriptions.cancel(subscription_id),
+                Err(SubscriptionError::NotParty)
+            );
+            // by the merchant
+            mocks.set_caller(accounts.bob);
+            assert_eq!(subscriptions.cancel(subscription_id), Ok(()));
+            // the remaining runs are still dispatched, and fail
+            for block in [10, 20] {
+                assert_eq!(mocks.scheduler.run_to_block(block).len(), 1);
+                assert_eq!(
+                    subscriptions.charge(subscription_id),
+                    Err(SubscriptionError::NotActive)
+                );
+            }
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.alice), 900);
+        }
+    }
+}
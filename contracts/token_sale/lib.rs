@@ -0,0 +1,554 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod token_sale {
+    use assets_extension::*;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use sp_arithmetic::helpers_128bit::multiply_by_rational_with_rounding;
+    use sp_arithmetic::Rounding;
+
+    /// Fixed point scale of tier prices, a price of `PRICE_SCALE` is one native
+    /// unit per token unit
+    pub const PRICE_SCALE: u128 = 1_000_000_000_000;
+    /// Maximum number of price tiers
+    pub const MAX_TIERS: u32 = 16;
+
+    #[ink(event)]
+    pub struct Purchased {
+        #[ink(topic)]
+        buyer: AccountId,
+        paid: Balance,
+        tokens: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Refunded {
+        #[ink(topic)]
+        buyer: AccountId,
+        refund: Balance,
+        burned: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum SaleError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        NotOwner,
+        /// Tiers are empty, more than `MAX_TIERS`, not increasing or free
+        InvalidTiers,
+        /// The end block is not after the start block
+        InvalidPeriod,
+        NotStarted,
+        Ended,
+        /// The sale is still running
+        NotEnded,
+        ZeroAmount,
+        /// Every tier is sold out
+        SoldOut,
+        /// The purchase goes above the cap of the buyer
+        CapExceeded,
+        /// The sale needs a whitelist proof
+        NotWhitelisted,
+        InvalidProof,
+        /// The soft cap was reached, purchases are final
+        SoftCapReached,
+        /// The soft cap was missed, proceeds go back to the buyers
+        SoftCapMissed,
+        NothingToRefund,
+        ProceedsWithdrawn,
+        /// Native transfer failed
+        TransferFailed,
+        Overflow,
+    }
+
+    impl From<AssetsError> for SaleError {
+        fn from(error: AssetsError) -> Self {
+            SaleError::Assets(error)
+        }
+    }
+
+    /// Tokens sold up to `cap`, counted from the start of the sale, cost `price`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Tier {
+        pub cap: Balance,
+        /// Native units per token unit, scaled by `PRICE_SCALE`
+        pub price: Balance,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum SaleState {
+        Pending,
+        Active,
+        Succeeded,
+        /// Ended below the soft cap
+        Failed,
+    }
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Purchase {
+        /// Native value paid
+        pub paid: Balance,
+        /// Tokens minted
+        pub tokens: Balance,
+    }
+
+    /// Sells a pallet asset for the native currency, minting it on purchase.
+    ///
+    /// The contract must be admin of the asset. If less than `soft_cap` is
+    /// raised by the end block, buyers get their payment back and the tokens
+    /// they still hold are burned.
+    #[ink(storage)]
+    pub struct TokenSale {
+        owner: AccountId,
+        asset_id: u128,
+        tiers: Vec<Tier>,
+        /// First block of the sale
+        start: BlockNumber,
+        /// First block after the sale
+        end: BlockNumber,
+        /// Native value to raise for the sale to succeed
+        soft_cap: Balance,
+        /// Maximum tokens bought per buyer
+        max_per_buyer: Balance,
+        /// Root of the `merkle_tree` of whitelisted buyers, the amount of
+        /// a leaf being the allowance of the buyer in tokens
+        whitelist_root: Option<[u8; 32]>,
+        sold: Balance,
+        raised: Balance,
+        proceeds_withdrawn: bool,
+        purchases: Mapping<AccountId, Purchase>,
+    }
+
+    impl TokenSale {
+        #[ink(constructor)]
+        pub fn new(
+            asset_id: u128,
+            tiers: Vec<Tier>,
+            start: BlockNumber,
+            end: BlockNumber,
+            soft_cap: Balance,
+            max_per_buyer: Balance,
+            whitelist_root: Option<[u8; 32]>,
+        ) -> Result<Self, SaleError> {
+            if tiers.is_empty()
+                || tiers.len() as u32 > MAX_TIERS
+                || tiers.iter().any(|tier| tier.price == 0)
+                || tiers.windows(2).any(|pair| pair[0].cap >= pair[1].cap)
+                || tiers[0].cap == 0
+            {
+                return Err(SaleError::InvalidTiers);
+            }
+            if end <= start {
+                return Err(SaleError::InvalidPeriod);
+            }
+            Ok(Self {
+                owner: Self::env().caller(),
+                asset_id,
+                tiers,
+                start,
+                end,
+                soft_cap,
+                max_per_buyer,
+                whitelist_root,
+                sold: 0,
+                raised: 0,
+                proceeds_withdrawn: false,
+                purchases: Mapping::default(),
+            })
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn tiers(&self) -> Vec<Tier> {
+            self.tiers.clone()
+        }
+
+        /// Tokens sold and native value raised
+        #[ink(message)]
+        pub fn totals(&self) -> (Balance, Balance) {
+            (self.sold, self.raised)
+        }
+
+        #[ink(message)]
+        pub fn purchase(&self, buyer: AccountId) -> Purchase {
+            self.purchases.get(buyer).unwrap_or_default()
+        }
+
+        #[ink(message)]
+        pub fn state(&self) -> SaleState {
+            let now = self.env().block_number();
+            if now < self.start {
+                SaleState::Pending
+            } else if now < self.end {
+                SaleState::Active
+            } else if self.raised >= self.soft_cap {
+                SaleState::Succeeded
+            } else {
+                SaleState::Failed
+            }
+        }
+
+        /// Tokens `value` buys at the current point of the curve, at most what is
+        /// left, and their cost
+        #[ink(message)]
+        pub fn quote(&self, value: Balance) -> Result<(Balance, Balance), SaleError> {
+            self.tokens_for(value)
+        }
+
+        /// Mints to the caller the tokens its transferred value buys, in a sale
+        /// without whitelist. What is not spent is sent back once the last
+        /// tier sells out.
+        #[ink(message, payable)]
+        pub fn buy(&mut self) -> Result<Balance, SaleError> {
+            if self.whitelist_root.is_some() {
+                return Err(SaleError::NotWhitelisted);
+            }
+            self.purchase_tokens(Balance::MAX)
+        }
+
+        /// Mints to the caller the tokens its transferred value buys, the caller
+        /// being leaf `index` of the whitelist with `allowance` tokens
+        #[ink(message, payable)]
+        pub fn buy_whitelisted(
+            &mut self,
+            index: u32,
+            allowance: Balance,
+            proof: Vec<[u8; 32]>,
+        ) -> Result<Balance, SaleError> {
+            let root = self.whitelist_root.ok_or(SaleError::InvalidProof)?;
+            let leaf = merkle_tree::leaf_hash(index, &self.env().caller(), allowance);
+            if !merkle_tree::verify(&root, leaf, &proof) {
+                return Err(SaleError::InvalidProof);
+            }
+            self.purchase_tokens(allowance)
+        }
+
+        /// Pays back the caller and burns the tokens it bought, up to what it
+        /// still holds, once the sale failed. The payment is refunded in
+        /// proportion of the tokens burned, the rest stays refundable against
+        /// the other tokens bought.
+        #[ink(message)]
+        pub fn refund(&mut self) -> Result<Balance, SaleError> {
+            match self.state() {
+                SaleState::Pending | SaleState::Active => return Err(SaleError::NotEnded),
+                SaleState::Succeeded => return Err(SaleError::SoftCapReached),
+                SaleState::Failed => (),
+            }
+            let buyer = self.env().caller();
+            let mut purchase = self.purchase(buyer);
+            let burned = AssetsExtension::balance_of(self.asset_id, buyer).min(purchase.tokens);
+            if burned == 0 {
+                return Err(SaleError::NothingToRefund);
+            }
+            let refund = mul_div(purchase.paid, burned, purchase.tokens)?;
+            AssetsExtension::burn(Origin::Address, self.asset_id, buyer, burned)?;
+            self.env()
+                .transfer(buyer, refund)
+                .map_err(|_| SaleError::TransferFailed)?;
+            purchase.paid -= refund;
+            purchase.tokens -= burned;
+            if purchase.tokens == 0 {
+                self.purchases.remove(buyer);
+            } else {
+                self.purchases.insert(buyer, &purchase);
+            }
+            self.env().emit_event(Refunded {
+                buyer,
+                refund,
+                burned,
+            });
+            Ok(refund)
+        }
+
+        /// Sends the proceeds to `to` once the sale succeeded
+        #[ink(message)]
+        pub fn withdraw_proceeds(&mut self, to: AccountId) -> Result<Balance, SaleError> {
+            self.ensure_owner()?;
+            match self.state() {
+                SaleState::Pending | SaleState::Active => return Err(SaleError::NotEnded),
+                SaleState::Failed => return Err(SaleError::SoftCapMissed),
+                SaleState::Succeeded => (),
+            }
+            if self.proceeds_withdrawn {
+                return Err(SaleError::ProceedsWithdrawn);
+            }
+            self.env()
+                .transfer(to, self.raised)
+                .map_err(|_| SaleError::TransferFailed)?;
+            self.proceeds_withdrawn = true;
+            Ok(self.raised)
+        }
+
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), SaleError> {
+            self.ensure_owner()?;
+            self.owner = new_owner;
+            Ok(())
+        }
+
+        fn purchase_tokens(&mut self, allowance: Balance) -> Result<Balance, SaleError> {
+            match self.state() {
+                SaleState::Pending => return Err(SaleError::NotStarted),
+                SaleState::Active => (),
+                _ => return Err(SaleError::Ended),
+            }
+            let value = self.env().transferred_value();
+            let (tokens, paid) = self.tokens_for(value)?;
+            if tokens == 0 {
+                return Err(SaleError::ZeroAmount);
+            }
+            let buyer = self.env().caller();
+            let mut purchase = self.purchase(buyer);
+            purchase.paid += paid;
+            purchase.tokens += tokens;
+            if purchase.tokens > self.max_per_buyer.min(allowance) {
+                return Err(SaleError::CapExceeded);
+            }
+
+            AssetsExtension::mint(Origin::Address, self.asset_id, buyer, tokens)?;
+            if value > paid {
+                self.env()
+                    .transfer(buyer, value - paid)
+                    .map_err(|_| SaleError::TransferFailed)?;
+            }
+            self.purchases.insert(buyer, &purchase);
+            self.sold += tokens;
+            self.raised += paid;
+            self.env().emit_event(Purchased {
+                buyer,
+                paid,
+                tokens,
+            });
+            Ok(tokens)
+        }
+
+        /// Tokens `value` buys walking up the tiers from the tokens sold so far,
+        /// and the value they cost. All of `value` is spent unless the last
+        /// tier sells out.
+        fn tokens_for(&self, value: Balance) -> Result<(Balance, Balance), SaleError> {
+            let mut bought = 0;
+            let mut remaining = value;
+            for tier in self.tiers.iter().filter(|tier| tier.cap > self.sold) {
+                let available = tier.cap - self.sold - bought;
+                let affordable = mul_div(remaining, PRICE_SCALE, tier.price)?;
+                if affordable <= available {
+                    return Ok((bought + affordable, value));
+                }
+                let cost = multiply_by_rational_with_rounding(
+                    available,
+                    tier.price,
+                    PRICE_SCALE,
+                    Rounding::Up,
+                )
+                .ok_or(SaleError::Overflow)?;
+                remaining = remaining.saturating_sub(cost);
+                bought += available;
+            }
+            if bought == 0 {
+                return Err(SaleError::SoldOut);
+            }
+            Ok((bought, value - remaining))
+        }
+
+        fn ensure_owner(&self) -> Result<(), SaleError> {
+            if self.env().caller() != self.owner {
+                return Err(SaleError::NotOwner);
+            }
+            Ok(())
+        }
+    }
+
+    /// `a * b / c` rounded down
+    fn mul_div(a: Balance, b: Balance, c: Balance) -> Result<Balance, SaleError> {
+        multiply_by_rational_with_rounding(a, b, c, Rounding::Down).ok_or(SaleError::Overflow)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use assets_extension::mock::AssetsMock;
+        use ink::env::{test, DefaultEnvironment};
+        use merkle_tree::{MerkleTree, Recipient};
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const ASSET: u128 = 1;
+
+        fn setup(whitelist_root: Option<[u8; 32]>) -> (TokenSale, AssetsMock) {
+            let mock = AssetsMock::register(AccountId::from(CONTRACT));
+            mock.create(ASSET, AccountId::from(CONTRACT), 1);
+            test::set_account_balance::<DefaultEnvironment>(AccountId::from(CONTRACT), 0);
+            let tiers = vec![
+                // 1 native per token, then 2 native per token
+                Tier {
+                    cap: 1_000,
+                    price: PRICE_SCALE,
+                },
+                Tier {
+                    cap: 2_000,
+                    price: 2 * PRICE_SCALE,
+                },
+            ];
+            let sale = TokenSale::new(ASSET, tiers, 2, 10, 1_000, 1_500, whitelist_root).unwrap();
+            (sale, mock)
+        }
+
+        /// Value transferred by the caller, credited to the contract like the
+        /// runtime does before the call
+        fn pay(value: Balance) {
+            let contract = AccountId::from(CONTRACT);
+            let balance = test::get_account_balance::<DefaultEnvironment>(contract).unwrap();
+            test::set_account_balance::<DefaultEnvironment>(contract, balance + value);
+            test::set_value_transferred::<DefaultEnvironment>(value);
+        }
+
+        fn advance_to(block: BlockNumber) {
+            while ink::env::block_number::<DefaultEnvironment>() < block {
+                test::advance_block::<DefaultEnvironment>();
+            }
+        }
+
+        #[ink::test]
+        fn sells_along_the_curve_within_caps() {
+            let (mut sale, mock) = setup(None);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            mock.set_caller(accounts.bob);
+            pay(100);
+            assert_eq!(sale.buy(), Err(SaleError::NotStarted));
+
+            advance_to(2);
+            pay(800);
+            assert_eq!(sale.buy(), Ok(800));
+            // 200 tokens at 1, then 150 at 2
+            pay(500);
+            assert_eq!(sale.buy(), Ok(350));
+            assert_eq!(mock.balance_of(ASSET, accounts.bob), 1_150);
+            pay(800);
+            assert_eq!(sale.buy(), Err(SaleError::CapExceeded));
+
+            // the last 850 tokens cost 1_700, the rest is sent back
+            mock.set_caller(accounts.charlie);
+            assert_eq!(sale.quote(1_800), Ok((850, 1_700)));
+            let balance =
+                test::get_account_balance::<DefaultEnvironment>(accounts.charlie).unwrap();
+            pay(1_800);
+            assert_eq!(sale.buy(), Ok(850));
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.charlie),
+                Ok(balance + 100)
+            );
+            assert_eq!(sale.purchase(accounts.charlie).paid, 1_700);
+            assert_eq!(sale.totals(), (2_000, 3_000));
+            pay(10);
+            assert_eq!(sale.buy(), Err(SaleError::SoldOut));
+
+            advance_to(10);
+            assert_eq!(sale.state(), SaleState::Succeeded);
+            assert_eq!(sale.refund(), Err(SaleError::SoftCapReached));
+            mock.set_caller(accounts.alice);
+            assert_eq!(sale.withdraw_proceeds(accounts.eve), Ok(3_000));
+            assert_eq!(
+                sale.withdraw_proceeds(accounts.eve),
+                Err(SaleError::ProceedsWithdrawn)
+            );
+        }
+
+        #[ink::test]
+        fn whitelist_limits_buyers_and_allowances() {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let recipients = [
+                Recipient {
+                    account: accounts.bob,
+                    amount: 100,
+                },
+                Recipient {
+                    account: accounts.charlie,
+                    amount: 300,
+                },
+            ];
+            let tree = MerkleTree::new(&recipients);
+            let (mut sale, mock) = setup(Some(tree.root()));
+            advance_to(2);
+
+            mock.set_caller(accounts.bob);
+            pay(50);
+            assert_eq!(sale.buy(), Err(SaleError::NotWhitelisted));
+            assert_eq!(
+                sale.buy_whitelisted(0, 1_000, tree.proof(0).unwrap()),
+                Err(SaleError::InvalidProof)
+            );
+            assert_eq!(
+                sale.buy_whitelisted(1, 300, tree.proof(1).unwrap()),
+                Err(SaleError::InvalidProof)
+            );
+            assert_eq!(sale.buy_whitelisted(0, 100, tree.proof(0).unwrap()), Ok(50));
+            pay(51);
+            assert_eq!(
+                sale.buy_whitelisted(0, 100, tree.proof(0).unwrap()),
+                Err(SaleError::CapExceeded)
+            );
+        }
+
+        #[ink::test]
+        fn refunds_below_soft_cap() {
+            let (mut sale, mock) = setup(None);
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            advance_to(2);
+            mock.set_caller(accounts.bob);
+            pay(400);
+            sale.buy().unwrap();
+            assert_eq!(sale.refund(), Err(SaleError::NotEnded));
+
+            advance_to(10);
+            assert_eq!(sale.state(), SaleState::Failed);
+            mock.set_caller(accounts.alice);
+            assert_eq!(
+                sale.withdraw_proceeds(accounts.alice),
+                Err(SaleError::SoftCapMissed)
+            );
+
+            // bob gave away a quarter of its tokens
+            mock.set_caller(accounts.bob);
+            AssetsExtension::transfer(Origin::Caller, ASSET, accounts.django, 100).unwrap();
+            let balance = test::get_account_balance::<DefaultEnvironment>(accounts.bob).unwrap();
+            assert_eq!(sale.refund(), Ok(300));
+            assert_eq!(
+                test::get_account_balance::<DefaultEnvironment>(accounts.bob),
+                Ok(balance + 300)
+            );
+            assert_eq!(mock.balance_of(ASSET, accounts.bob), 0);
+            assert_eq!(mock.asset(ASSET).unwrap().supply, 100);
+            assert_eq!(
+                sale.purchase(accounts.bob),
+                Purchase {
+                    paid: 100,
+                    tokens: 100
+                }
+            );
+            assert_eq!(sale.refund(), Err(SaleError::NothingToRefund));
+
+            // the rest is refunded once bob holds the tokens again
+            mock.set_caller(accounts.django);
+            AssetsExtension::transfer(Origin::Caller, ASSET, accounts.bob, 100).unwrap();
+            mock.set_caller(accounts.bob);
+            assert_eq!(sale.refund(), Ok(100));
+            assert_eq!(mock.asset(ASSET).unwrap().supply, 0);
+            assert_eq!(sale.purchase(accounts.bob), Purchase::default());
+            assert_eq!(sale.refund(), Err(SaleError::NothingToRefund));
+        }
+    }
+}
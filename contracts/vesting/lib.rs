@@ -0,0 +1,421 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod vesting {
+    use assets_extension::{AssetsError, AssetsExtension};
+    use ink::env::call::Selector;
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use scheduler_extension::{
+        ContractCallInput, SchedulerError, SchedulerExtension, CALL_GAS_LIMIT, CALL_MAX_WEIGHT,
+    };
+    use sp_arithmetic::helpers_128bit::multiply_by_rational_with_rounding;
+    use sp_arithmetic::Rounding;
+
+    /// Maximum number of vesting schedules per beneficiary
+    pub const MAX_SCHEDULES: u32 = 8;
+
+    #[ink(event)]
+    pub struct ScheduleCreated {
+        #[ink(topic)]
+        beneficiary: AccountId,
+        #[ink(topic)]
+        funder: AccountId,
+        schedule: VestingSchedule,
+    }
+
+    #[ink(event)]
+    pub struct Released {
+        #[ink(topic)]
+        beneficiary: AccountId,
+        value: Balance,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum VestingError {
+        /// Error from the assets pallet
+        Assets(AssetsError),
+        /// Error from the scheduler pallet
+        Scheduler(SchedulerError),
+        NotOwner,
+        ZeroAmount,
+        /// Duration is zero or the cliff is after the end of the schedule
+        InvalidSchedule,
+        TooManySchedules,
+        /// Nothing has vested since the last release
+        NothingToRelease,
+    }
+
+    impl From<AssetsError> for VestingError {
+        fn from(error: AssetsError) -> Self {
+            VestingError::Assets(error)
+        }
+    }
+
+    impl From<SchedulerError> for VestingError {
+        fn from(error: SchedulerError) -> Self {
+            VestingError::Scheduler(error)
+        }
+    }
+
+    /// Linear vesting of `total` from `start` to `start + duration`, nothing of
+    /// which can be released before `start + cliff`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct VestingSchedule {
+        pub total: Balance,
+        pub released: Balance,
+        pub start: BlockNumber,
+        pub cliff: BlockNumber,
+        pub duration: BlockNumber,
+    }
+
+    impl VestingSchedule {
+        /// Amount vested at block `now`, released or not
+        pub fn vested(&self, now: BlockNumber) -> Balance {
+            let elapsed = now.saturating_sub(self.start);
+            if elapsed < self.cliff {
+                0
+            } else if elapsed >= self.duration {
+                self.total
+            } else {
+                multiply_by_rational_with_rounding(
+                    self.total,
+                    elapsed as u128,
+                    self.duration as u128,
+                    Rounding::Down,
+                )
+                .unwrap_or(0)
+            }
+        }
+    }
+
+    /// Escrows a pallet asset and releases it to beneficiaries as it vests.
+    ///
+    /// Only the owner creates schedules, so nobody else can fill the
+    /// `MAX_SCHEDULES` slots of a beneficiary with dust schedules.
+    #[ink(storage)]
+    pub struct Vesting {
+        owner: AccountId,
+        asset_id: u128,
+        schedules: Mapping<AccountId, Vec<VestingSchedule>>,
+    }
+
+    impl Vesting {
+        #[ink(constructor)]
+        pub fn new(asset_id: u128) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                asset_id,
+                schedules: Mapping::default(),
+            }
+        }
+
+        #[ink(message)]
+        pub fn owner(&self) -> AccountId {
+            self.owner
+        }
+
+        #[ink(message)]
+        pub fn asset_id(&self) -> u128 {
+            self.asset_id
+        }
+
+        #[ink(message)]
+        pub fn schedules(&self, beneficiary: AccountId) -> Vec<VestingSchedule> {
+            self.schedules.get(beneficiary).unwrap_or_default()
+        }
+
+        /// Amount `beneficiary` can release now
+        #[ink(message)]
+        pub fn releasable(&self, beneficiary: AccountId) -> Balance {
+            let now = self.env().block_number();
+            self.schedules(beneficiary)
+                .iter()
+                .map(|schedule| schedule.vested(now) - schedule.released)
+                .sum()
+        }
+
+        /// Escrows `amount` of the asset from the owner, vesting to `beneficiary`.
+        /// Owner should approve contract address as spender beforehand
+        #[ink(message)]
+        pub fn create_schedule(
+            &mut self,
+            beneficiary: AccountId,
+            amount: Balance,
+            start: BlockNumber,
+            cliff: BlockNumber,
+            duration: BlockNumber,
+        ) -> Result<(), VestingError> {
+            self.ensure_owner()?;
+            if amount == 0 {
+                return Err(VestingError::ZeroAmount);
+            }
+            if duration == 0 || cliff > duration {
+                return Err(VestingError::InvalidSchedule);
+            }
+            let mut schedules = self.schedules(beneficiary);
+            if schedules.len() as u32 >= MAX_SCHEDULES {
+                return Err(VestingError::TooManySchedules);
+            }
+            let funder = self.env().caller();
+            AssetsExtension::transfer_approved(
+                assets_extension::Origin::Address,
+                self.asset_id,
+                funder,
+                self.env().account_id(),
+                amount,
+            )?;
+            let schedule = VestingSchedule {
+                total: amount,
+                released: 0,
+                start,
+                cliff,
+                duration,
+            };
+            schedules.push(schedule);
+            self.schedules.insert(beneficiary, &schedules);
+            self.env().emit_event(ScheduleCreated {
+                beneficiary,
+                funder,
+                schedule,
+            });
+            Ok(())
+        }
+
+        /// Sends the vested portion of the caller's schedules to the caller
+        #[ink(message)]
+        pub fn release(&mut self) -> Result<Balance, VestingError> {
+            let caller = self.env().caller();
+            self.release_for(caller)
+        }
+
+        /// Sends the vested portion of the schedules of `beneficiary` to it.
+        /// Anyone can call it, e.g. the scheduler.
+        #[ink(message)]
+        pub fn release_for(&mut self, beneficiary: AccountId) -> Result<Balance, VestingError> {
+            let now = self.env().block_number();
+            let mut schedules = self.schedules(beneficiary);
+            let mut value = 0;
+            for schedule in schedules.iter_mut() {
+                let vested = schedule.vested(now);
+                value += vested - schedule.released;
+                schedule.released = vested;
+            }
+            if value == 0 {
+                return Err(VestingError::NothingToRelease);
+            }
+            // fully released schedules are dropped
+            schedules.retain(|schedule| schedule.released < schedule.total);
+            if schedules.is_empty() {
+                self.schedules.remove(beneficiary);
+            } else {
+                self.schedules.insert(beneficiary, &schedules);
+            }
+            AssetsExtension::transfer(
+                assets_extension::Origin::Address,
+                self.asset_id,
+                beneficiary,
+                value,
+            )?;
+            self.env().emit_event(Released { beneficiary, value });
+            Ok(value)
+        }
+
+        /// Schedules `release_for(caller)` at block `when`, then every `period`
+        /// blocks, `count` times in total. The calls are dispatched as the caller,
+        /// who pays their storage deposit and can cancel them with the scheduler.
+        #[ink(message)]
+        pub fn schedule_releases(
+            &mut self,
+            when: BlockNumber,
+            period: BlockNumber,
+            count: u32,
+        ) -> Result<(), VestingError> {
+            let call_input = self.release_call(self.env().caller());
+            SchedulerExtension::schedule(
+                scheduler_extension::Origin::Caller,
+                when,
+                Some((period, count)),
+                0,
+                call_input,
+            )?;
+            Ok(())
+        }
+
+        #[ink(message)]
+        pub fn transfer_ownership(&mut self, new_owner: AccountId) -> Result<(), VestingError> {
+            self.ensure_owner()?;
+            self.owner = new_owner;
+            Ok(())
+        }
+
+        fn ensure_owner(&self) -> Result<(), VestingError> {
+            if self.env().caller() != self.owner {
+                return Err(VestingError::NotOwner);
+            }
+            Ok(())
+        }
+
+        fn release_call(&self, beneficiary: AccountId) -> ContractCallInput {
+            let mut data = Selector::new(ink::selector_bytes!("release_for"))
+                .to_bytes()
+                .to_vec();
+            scale::Encode::encode_to(&beneficiary, &mut data);
+            ContractCallInput {
+                dest: self.env().account_id(),
+                data,
+                gas_limit: CALL_GAS_LIMIT,
+                storage_deposit_limit: None,
+                value: 0,
+                max_weight: CALL_MAX_WEIGHT,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use ink::env::{test, DefaultEnvironment};
+        use scheduler_extension::mock::Mocks;
+
+        const CONTRACT: [u8; 32] = [0xC0; 32];
+        const ASSET: u128 = 1;
+
+        fn setup() -> (Vesting, Mocks, test::DefaultAccounts<DefaultEnvironment>) {
+            let accounts = test::default_accounts::<DefaultEnvironment>();
+            let mocks = Mocks::register(AccountId::from(CONTRACT));
+            mocks.assets.create(ASSET, accounts.alice, 1);
+            mocks.assets.mint(ASSET, accounts.alice, 1_000);
+            mocks
+                .assets
+                .approve(ASSET, accounts.alice, AccountId::from(CONTRACT), 1_000);
+            mocks.set_caller(accounts.alice);
+            (Vesting::new(ASSET), mocks, accounts)
+        }
+
+        #[ink::test]
+        fn vests_linearly_after_cliff() {
+            let (mut vesting, mocks, accounts) = setup();
+            vesting
+                .create_schedule(accounts.bob, 1_000, 10, 20, 100)
+                .unwrap();
+            assert_eq!(
+                mocks.assets.balance_of(ASSET, AccountId::from(CONTRACT)),
+                1_000
+            );
+
+            mocks.set_caller(accounts.bob);
+            mocks.scheduler.run_to_block(29);
+            assert_eq!(vesting.releasable(accounts.bob), 0);
+            assert_eq!(vesting.release(), Err(VestingError::NothingToRelease));
+
+            mocks.scheduler.run_to_block(30);
+            assert_eq!(vesting.release(), Ok(200));
+            mocks.scheduler.run_to_block(85);
+            assert_eq!(vesting.releasable(accounts.bob), 550);
+            mocks.scheduler.run_to_block(200);
+            assert_eq!(vesting.release(), Ok(800));
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.bob), 1_000);
+            assert!(vesting.schedules(accounts.bob).is_empty());
+        }
+
+        #[ink::test]
+        fn scheduler_pushes_releases() {
+            let (mut vesting, mocks, accounts) = setup();
+            vesting
+                .create_schedule(accounts.bob, 1_000, 0, 0, 40)
+                .unwrap();
+
+            mocks.set_caller(accounts.bob);
+            vesting.schedule_releases(10, 10, 4).unwrap();
+            for block in [10, 20, 30, 40] {
+                let due = mocks.scheduler.run_to_block(block);
+                assert_eq!(due.len(), 1);
+                assert_eq!(due[0].1.origin, accounts.bob);
+                assert_eq!(due[0].1.call, vesting.release_call(accounts.bob));
+                // dispatched by the scheduler
+                assert_eq!(vesting.release_for(accounts.bob), Ok(250));
+            }
+            assert!(mocks.scheduler.run_to_block(100).is_empty());
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.bob), 1_000);
+        }
+
+        #[ink::test]
+        fn scheduling_releases_needs_caller_origin() {
+            let (mut vesting, mocks, accounts) = setup();
+            vesting
+                .create_schedule(accounts.bob, 1_000, 0, 0, 40)
+                .unwrap();
+            mocks.scheduler.reject_caller_origin();
+
+            mocks.set_caller(accounts.bob);
+            assert_eq!(
+                vesting.schedule_releases(10, 10, 4),
+                Err(VestingError::Scheduler(
+                    SchedulerError::OriginCannotBeCaller
+                ))
+            );
+            // releases can still be pushed by hand
+            mocks.scheduler.run_to_block(20);
+            assert_eq!(vesting.release(), Ok(500));
+        }
+
+        #[ink::test]
+        fn only_owner_creates_schedules() {
+            let (mut vesting, mocks, accounts) = setup();
+            mocks.assets.mint(ASSET, accounts.charlie, 100);
+            mocks
+                .assets
+                .approve(ASSET, accounts.charlie, AccountId::from(CONTRACT), 100);
+
+            // dust schedules cannot use up the slots of a beneficiary
+            mocks.set_caller(accounts.charlie);
+            assert_eq!(
+                vesting.create_schedule(accounts.bob, 1, 0, 0, 4),
+                Err(VestingError::NotOwner)
+            );
+            assert_eq!(
+                vesting.transfer_ownership(accounts.charlie),
+                Err(VestingError::NotOwner)
+            );
+            assert!(vesting.schedules(accounts.bob).is_empty());
+
+            mocks.set_caller(accounts.alice);
+            vesting.transfer_ownership(accounts.charlie).unwrap();
+            mocks.set_caller(accounts.charlie);
+            vesting.create_schedule(accounts.bob, 100, 0, 0, 4).unwrap();
+            assert_eq!(mocks.assets.balance_of(ASSET, accounts.charlie), 0);
+            assert_eq!(vesting.schedules(accounts.bob).len(), 1);
+        }
+
+        #[ink::test]
+        fn invalid_schedules_are_rejected() {
+            let (mut vesting, _, accounts) = setup();
+
+            assert_eq!(
+                vesting.create_schedule(accounts.bob, 10, 0, 5, 4),
+                Err(VestingError::InvalidSchedule)
+            );
+            assert_eq!(
+                vesting.create_schedule(accounts.bob, 0, 0, 0, 4),
+                Err(VestingError::ZeroAmount)
+            );
+            assert_eq!(
+                vesting.create_schedule(accounts.bob, 2_000, 0, 0, 4),
+                Err(VestingError::Assets(AssetsError::Unapproved))
+            );
+            for _ in 0..MAX_SCHEDULES {
+                vesting.create_schedule(accounts.bob, 1, 0, 0, 4).unwrap();
+            }
+            assert_eq!(
+                vesting.create_schedule(accounts.bob, 1, 0, 0, 4),
+                Err(VestingError::TooManySchedules)
+            );
+        }
+    }
+}
@@ -5,6 +5,9 @@ use ink::prelude::vec::Vec;
 use ink::env::{DefaultEnvironment, Environment};
 use scale::{Decode, Encode};
 
+#[cfg(feature = "std")]
+pub mod mock;
+
 type Balance = <DefaultEnvironment as Environment>::Balance;
 type AccountId = <DefaultEnvironment as Environment>::AccountId;
 
@@ -221,7 +224,7 @@ pub enum AssetsError {
     IncorrectStatus = 18,
     /// The asset should be frozen before the given operation.
     NotFrozen = 19,
-    /// Origin Caller is not supported
+    /// The runtime does not support `Origin::Caller`
     OriginCannotBeCaller = 98,
     /// Unknown error
     RuntimeError = 99,
@@ -266,13 +269,30 @@ impl From<scale::Error> for AssetsError {
     }
 }
 
-#[derive(Clone, Copy, Decode, Encode)]
+/// Maps a pallet error to the matching PSP22 error, keeping the pallet
+/// variant as `Custom` when PSP22 has no equivalent.
+#[cfg(feature = "psp22")]
+pub fn to_psp22_error(error: AssetsError) -> openbrush::contracts::psp22::PSP22Error {
+    use openbrush::contracts::psp22::PSP22Error;
+    match error {
+        AssetsError::BalanceLow => PSP22Error::InsufficientBalance,
+        AssetsError::Unapproved => PSP22Error::InsufficientAllowance,
+        error => PSP22Error::Custom(ink::prelude::format!("{:?}", error).into()),
+    }
+}
+
+/// Account a chain extension call is dispatched as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Decode, Encode)]
 #[cfg_attr(
     feature = "std",
     derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
 )]
 pub enum Origin {
+    /// The immediate caller of the contract, as returned by `env().caller()`.
+    /// When the contract is called by another contract this is the calling
+    /// contract, never the account which signed the transaction.
     Caller,
+    /// The contract itself, as returned by `env().account_id()`
     Address,
 }
 
@@ -281,3 +301,110 @@ impl Default for Origin {
         Self::Address
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::AssetsMock;
+    use ink::env::test;
+
+    const CONTRACT: [u8; 32] = [0xC0; 32];
+    const OTHER_CONTRACT: [u8; 32] = [0xC1; 32];
+    const ASSET: u128 = 1;
+
+    fn setup() -> (AssetsMock, test::DefaultAccounts<DefaultEnvironment>) {
+        let accounts = test::default_accounts::<DefaultEnvironment>();
+        let mock = AssetsMock::register(AccountId::from(CONTRACT));
+        mock.create(ASSET, AccountId::from(CONTRACT), 1);
+        mock.mint(ASSET, accounts.alice, 100);
+        mock.set_caller(accounts.alice);
+        (mock, accounts)
+    }
+
+    #[ink::test]
+    fn caller_origin_moves_caller_assets() {
+        let (mock, accounts) = setup();
+
+        assert_eq!(
+            AssetsExtension::transfer(Origin::Caller, ASSET, accounts.bob, 40),
+            Ok(())
+        );
+        assert_eq!(mock.balance_of(ASSET, accounts.alice), 60);
+        assert_eq!(mock.balance_of(ASSET, accounts.bob), 40);
+        assert_eq!(
+            AssetsExtension::transfer(Origin::Address, ASSET, accounts.bob, 40),
+            Err(AssetsError::BalanceLow)
+        );
+    }
+
+    #[ink::test]
+    fn caller_origin_approval_lets_contract_pull() {
+        let (mock, accounts) = setup();
+        let contract = AccountId::from(CONTRACT);
+
+        AssetsExtension::approve_transfer(Origin::Caller, ASSET, contract, 30).unwrap();
+        assert_eq!(mock.allowance(ASSET, accounts.alice, contract), 30);
+        assert_eq!(
+            AssetsExtension::transfer_approved(
+                Origin::Address,
+                ASSET,
+                accounts.alice,
+                contract,
+                30
+            ),
+            Ok(())
+        );
+        assert_eq!(mock.balance_of(ASSET, contract), 30);
+        assert_eq!(mock.allowance(ASSET, accounts.alice, contract), 0);
+    }
+
+    #[ink::test]
+    fn caller_origin_is_immediate_caller() {
+        let (mock, accounts) = setup();
+        let other = AccountId::from(OTHER_CONTRACT);
+        mock.mint(ASSET, other, 10);
+
+        // called by another contract, the calling contract is the origin
+        // and the account which signed the transaction is not involved
+        mock.set_caller(other);
+        AssetsExtension::transfer(Origin::Caller, ASSET, accounts.bob, 10).unwrap();
+        assert_eq!(mock.balance_of(ASSET, other), 0);
+        assert_eq!(mock.balance_of(ASSET, accounts.alice), 100);
+        assert_eq!(
+            AssetsExtension::transfer(Origin::Caller, ASSET, accounts.bob, 10),
+            Err(AssetsError::BalanceLow)
+        );
+    }
+
+    #[ink::test]
+    fn caller_origin_creates_asset_owned_by_caller() {
+        let (mock, accounts) = setup();
+
+        AssetsExtension::create(Origin::Caller, 2, accounts.alice, 1).unwrap();
+        assert_eq!(mock.asset(2).unwrap().owner, accounts.alice);
+        assert_eq!(
+            AssetsExtension::set_metadata(
+                Origin::Address,
+                2,
+                b"Token".to_vec(),
+                b"TKN".to_vec(),
+                18
+            ),
+            Err(AssetsError::NoPermission)
+        );
+        AssetsExtension::set_metadata(Origin::Caller, 2, b"Token".to_vec(), b"TKN".to_vec(), 18)
+            .unwrap();
+        assert_eq!(AssetsExtension::metadata_symbol(2), b"TKN".to_vec());
+    }
+
+    #[ink::test]
+    fn runtime_without_caller_origin_fails() {
+        let (mock, accounts) = setup();
+        mock.reject_caller_origin();
+
+        assert_eq!(
+            AssetsExtension::transfer(Origin::Caller, ASSET, accounts.bob, 10),
+            Err(AssetsError::OriginCannotBeCaller)
+        );
+    }
+}
@@ -0,0 +1,448 @@
+//! Off-chain mock of the pallet-assets chain extension.
+//!
+//! Register it at the start of an `#[ink::test]` to exercise contracts built on
+//! [`AssetsExtension`](crate::AssetsExtension) without a running node.
+//! `Origin::Address` resolves to the contract given to [`AssetsMock::register`],
+//! `Origin::Caller` to the account set with [`AssetsMock::set_caller`].
+//! Existential deposits and freezing are not modelled.
+
+use crate::{AssetsError, Origin};
+use ink::env::test::ChainExtension;
+use ink::env::{DefaultEnvironment, Environment};
+use scale::{Decode, Encode};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+type Balance = <DefaultEnvironment as Environment>::Balance;
+type AccountId = <DefaultEnvironment as Environment>::AccountId;
+
+/// Maximum length of the asset name and symbol
+pub const STRING_LIMIT: usize = 50;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetDetails {
+    pub owner: AccountId,
+    pub admin: AccountId,
+    pub min_balance: Balance,
+    pub supply: Balance,
+    pub name: Vec<u8>,
+    pub symbol: Vec<u8>,
+    pub decimals: u8,
+}
+
+impl AssetDetails {
+    fn new(owner: AccountId, admin: AccountId, min_balance: Balance) -> Self {
+        Self {
+            owner,
+            admin,
+            min_balance,
+            supply: 0,
+            name: Vec::new(),
+            symbol: Vec::new(),
+            decimals: 0,
+        }
+    }
+}
+
+struct State {
+    contract: AccountId,
+    caller: AccountId,
+    caller_origin_supported: bool,
+    assets: BTreeMap<u128, AssetDetails>,
+    balances: BTreeMap<(u128, AccountId), Balance>,
+    /// Approved amount per (asset, owner, delegate)
+    approvals: BTreeMap<(u128, AccountId, AccountId), Balance>,
+}
+
+impl State {
+    fn origin(&self, origin: Origin) -> Result<AccountId, AssetsError> {
+        match origin {
+            Origin::Address => Ok(self.contract),
+            Origin::Caller if self.caller_origin_supported => Ok(self.caller),
+            Origin::Caller => Err(AssetsError::OriginCannotBeCaller),
+        }
+    }
+
+    fn asset(&mut self, id: u128) -> Result<&mut AssetDetails, AssetsError> {
+        self.assets.get_mut(&id).ok_or(AssetsError::Unknown)
+    }
+
+    fn balance_of(&self, id: u128, who: AccountId) -> Balance {
+        self.balances.get(&(id, who)).copied().unwrap_or_default()
+    }
+
+    fn allowance(&self, id: u128, owner: AccountId, delegate: AccountId) -> Balance {
+        self.approvals
+            .get(&(id, owner, delegate))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn create(
+        &mut self,
+        origin: Origin,
+        id: u128,
+        admin: AccountId,
+        min_balance: Balance,
+    ) -> Result<(), AssetsError> {
+        let owner = self.origin(origin)?;
+        if self.assets.contains_key(&id) {
+            return Err(AssetsError::InUse);
+        }
+        if min_balance == 0 {
+            return Err(AssetsError::MinBalanceZero);
+        }
+        self.assets
+            .insert(id, AssetDetails::new(owner, admin, min_balance));
+        Ok(())
+    }
+
+    fn move_balance(
+        &mut self,
+        id: u128,
+        from: AccountId,
+        to: AccountId,
+        amount: Balance,
+    ) -> Result<(), AssetsError> {
+        self.asset(id)?;
+        let balance = self.balance_of(id, from);
+        if balance < amount {
+            return Err(AssetsError::BalanceLow);
+        }
+        self.balances.insert((id, from), balance - amount);
+        *self.balances.entry((id, to)).or_default() += amount;
+        Ok(())
+    }
+
+    fn mint(
+        &mut self,
+        origin: Origin,
+        id: u128,
+        beneficiary: AccountId,
+        amount: Balance,
+    ) -> Result<(), AssetsError> {
+        let origin = self.origin(origin)?;
+        let asset = self.asset(id)?;
+        if asset.admin != origin {
+            return Err(AssetsError::NoPermission);
+        }
+        asset.supply += amount;
+        *self.balances.entry((id, beneficiary)).or_default() += amount;
+        Ok(())
+    }
+
+    /// Burns up to `amount`, like the pallet does
+    fn burn(
+        &mut self,
+        origin: Origin,
+        id: u128,
+        who: AccountId,
+        amount: Balance,
+    ) -> Result<(), AssetsError> {
+        let origin = self.origin(origin)?;
+        let balance = self.balance_of(id, who);
+        let asset = self.asset(id)?;
+        if asset.admin != origin {
+            return Err(AssetsError::NoPermission);
+        }
+        let burned = amount.min(balance);
+        asset.supply -= burned;
+        self.balances.insert((id, who), balance - burned);
+        Ok(())
+    }
+
+    /// Approvals add up to the existing one, like the pallet does
+    fn approve_transfer(
+        &mut self,
+        origin: Origin,
+        id: u128,
+        delegate: AccountId,
+        amount: Balance,
+    ) -> Result<(), AssetsError> {
+        let owner = self.origin(origin)?;
+        self.asset(id)?;
+        *self.approvals.entry((id, owner, delegate)).or_default() += amount;
+        Ok(())
+    }
+
+    fn cancel_approval(
+        &mut self,
+        origin: Origin,
+        id: u128,
+        delegate: AccountId,
+    ) -> Result<(), AssetsError> {
+        let owner = self.origin(origin)?;
+        self.asset(id)?;
+        self.approvals
+            .remove(&(id, owner, delegate))
+            .map(|_| ())
+            .ok_or(AssetsError::Unknown)
+    }
+
+    fn transfer_approved(
+        &mut self,
+        origin: Origin,
+        id: u128,
+        owner: AccountId,
+        destination: AccountId,
+        amount: Balance,
+    ) -> Result<(), AssetsError> {
+        let delegate = self.origin(origin)?;
+        self.asset(id)?;
+        let approved = self.allowance(id, owner, delegate);
+        if approved < amount {
+            return Err(AssetsError::Unapproved);
+        }
+        self.move_balance(id, owner, destination, amount)?;
+        if approved == amount {
+            self.approvals.remove(&(id, owner, delegate));
+        } else {
+            self.approvals
+                .insert((id, owner, delegate), approved - amount);
+        }
+        Ok(())
+    }
+
+    fn set_metadata(
+        &mut self,
+        origin: Origin,
+        id: u128,
+        name: Vec<u8>,
+        symbol: Vec<u8>,
+        decimals: u8,
+    ) -> Result<(), AssetsError> {
+        let origin = self.origin(origin)?;
+        let asset = self.asset(id)?;
+        if asset.owner != origin {
+            return Err(AssetsError::NoPermission);
+        }
+        if name.len() > STRING_LIMIT || symbol.len() > STRING_LIMIT {
+            return Err(AssetsError::BadMetadata);
+        }
+        asset.name = name;
+        asset.symbol = symbol;
+        asset.decimals = decimals;
+        Ok(())
+    }
+
+    fn transfer_ownership(
+        &mut self,
+        origin: Origin,
+        id: u128,
+        owner: AccountId,
+    ) -> Result<(), AssetsError> {
+        let origin = self.origin(origin)?;
+        let asset = self.asset(id)?;
+        if asset.owner != origin {
+            return Err(AssetsError::NoPermission);
+        }
+        asset.owner = owner;
+        Ok(())
+    }
+}
+
+/// Handle to the mocked pallet-assets state.
+///
+/// Cloning the handle shares the same state, so a test can keep one to set up
+/// assets and inspect balances while the registered extension methods serve the contract.
+#[derive(Clone)]
+pub struct AssetsMock {
+    state: Rc<RefCell<State>>,
+}
+
+impl AssetsMock {
+    /// Registers every pallet-assets method in the off-chain environment.
+    /// `contract` becomes the callee of the test and the account behind
+    /// `Origin::Address`, the current test caller the one behind `Origin::Caller`.
+    pub fn register(contract: AccountId) -> Self {
+        ink::env::test::set_callee::<DefaultEnvironment>(contract);
+        let mock = Self {
+            state: Rc::new(RefCell::new(State {
+                contract,
+                caller: ink::env::caller::<DefaultEnvironment>(),
+                caller_origin_supported: true,
+                assets: Default::default(),
+                balances: Default::default(),
+                approvals: Default::default(),
+            })),
+        };
+        for func_id in 0x20001..=0x2000F {
+            ink::env::test::register_chain_extension(Method {
+                func_id,
+                state: mock.state.clone(),
+            });
+        }
+        mock
+    }
+
+    /// Sets the caller of the contract, both in the test environment and as
+    /// the account behind `Origin::Caller`.
+    pub fn set_caller(&self, caller: AccountId) {
+        ink::env::test::set_caller::<DefaultEnvironment>(caller);
+        self.state.borrow_mut().caller = caller;
+    }
+
+    /// Makes `Origin::Caller` fail with `OriginCannotBeCaller`, like runtimes
+    /// which only support `Origin::Address` do.
+    pub fn reject_caller_origin(&self) {
+        self.state.borrow_mut().caller_origin_supported = false;
+    }
+
+    /// Creates an asset owned and administered by `owner`, bypassing the origin.
+    pub fn create(&self, id: u128, owner: AccountId, min_balance: Balance) {
+        self.state
+            .borrow_mut()
+            .assets
+            .insert(id, AssetDetails::new(owner, owner, min_balance));
+    }
+
+    /// Mints `amount` of asset `id` to `who`, bypassing the admin check.
+    pub fn mint(&self, id: u128, who: AccountId, amount: Balance) {
+        let mut state = self.state.borrow_mut();
+        state
+            .assets
+            .get_mut(&id)
+            .expect("asset must be created before minting")
+            .supply += amount;
+        *state.balances.entry((id, who)).or_default() += amount;
+    }
+
+    /// Sets the metadata of asset `id`, bypassing the owner check.
+    pub fn set_metadata(&self, id: u128, name: &[u8], symbol: &[u8], decimals: u8) {
+        let mut state = self.state.borrow_mut();
+        let asset = state
+            .assets
+            .get_mut(&id)
+            .expect("asset must be created before setting metadata");
+        asset.name = name.to_vec();
+        asset.symbol = symbol.to_vec();
+        asset.decimals = decimals;
+    }
+
+    /// Approves `delegate` to spend `amount` of asset `id` held by `owner`.
+    pub fn approve(&self, id: u128, owner: AccountId, delegate: AccountId, amount: Balance) {
+        *self
+            .state
+            .borrow_mut()
+            .approvals
+            .entry((id, owner, delegate))
+            .or_default() += amount;
+    }
+
+    pub fn asset(&self, id: u128) -> Option<AssetDetails> {
+        self.state.borrow().assets.get(&id).cloned()
+    }
+
+    pub fn balance_of(&self, id: u128, who: AccountId) -> Balance {
+        self.state.borrow().balance_of(id, who)
+    }
+
+    pub fn allowance(&self, id: u128, owner: AccountId, delegate: AccountId) -> Balance {
+        self.state.borrow().allowance(id, owner, delegate)
+    }
+}
+
+struct Method {
+    func_id: u32,
+    state: Rc<RefCell<State>>,
+}
+
+impl Method {
+    fn read<T: Encode>(output: &mut Vec<u8>, value: T) -> u32 {
+        value.encode_to(output);
+        0
+    }
+
+    fn status(output: &mut Vec<u8>, result: Result<(), AssetsError>) -> u32 {
+        match result {
+            Ok(()) => Self::read(output, result),
+            Err(e) => e as u32,
+        }
+    }
+}
+
+impl ChainExtension for Method {
+    fn func_id(&self) -> u32 {
+        self.func_id
+    }
+
+    fn call(&mut self, input: &[u8], output: &mut Vec<u8>) -> u32 {
+        let input = Vec::<u8>::decode(&mut &input[..]).expect("input is a byte vector");
+        let mut input = &input[..];
+        let mut state = self.state.borrow_mut();
+        match self.func_id {
+            0x20001 => {
+                let (origin, id, admin, min_balance) = Decode::decode(&mut input).unwrap();
+                Self::status(output, state.create(origin, id, admin, min_balance))
+            }
+            0x20002 => {
+                let (origin, id, target, amount) =
+                    <(Origin, u128, AccountId, Balance)>::decode(&mut input).unwrap();
+                let result = state
+                    .origin(origin)
+                    .and_then(|from| state.move_balance(id, from, target, amount));
+                Self::status(output, result)
+            }
+            0x20003 => {
+                let (origin, id, beneficiary, amount) = Decode::decode(&mut input).unwrap();
+                Self::status(output, state.mint(origin, id, beneficiary, amount))
+            }
+            0x20004 => {
+                let (origin, id, who, amount) = Decode::decode(&mut input).unwrap();
+                Self::status(output, state.burn(origin, id, who, amount))
+            }
+            0x20005 => {
+                let (id, who) = Decode::decode(&mut input).unwrap();
+                Self::read(output, state.balance_of(id, who))
+            }
+            0x20006 => {
+                let id = u128::decode(&mut input).unwrap();
+                let supply = state.assets.get(&id).map(|a| a.supply);
+                Self::read(output, supply.unwrap_or_default())
+            }
+            0x20007 => {
+                let (id, owner, delegate) = Decode::decode(&mut input).unwrap();
+                Self::read(output, state.allowance(id, owner, delegate))
+            }
+            0x20008 => {
+                let (origin, id, delegate, amount) = Decode::decode(&mut input).unwrap();
+                Self::status(output, state.approve_transfer(origin, id, delegate, amount))
+            }
+            0x20009 => {
+                let (origin, id, delegate) = Decode::decode(&mut input).unwrap();
+                Self::status(output, state.cancel_approval(origin, id, delegate))
+            }
+            0x2000A => {
+                let (origin, id, owner, destination, amount) = Decode::decode(&mut input).unwrap();
+                let result = state.transfer_approved(origin, id, owner, destination, amount);
+                Self::status(output, result)
+            }
+            0x2000B => {
+                let (origin, id, name, symbol, decimals) = Decode::decode(&mut input).unwrap();
+                Self::status(
+                    output,
+                    state.set_metadata(origin, id, name, symbol, decimals),
+                )
+            }
+            0x2000C..=0x2000E => {
+                let id = u128::decode(&mut input).unwrap();
+                let asset = state.assets.get(&id);
+                match self.func_id {
+                    0x2000C => {
+                        Self::read(output, asset.map(|a| a.name.clone()).unwrap_or_default())
+                    }
+                    0x2000D => {
+                        Self::read(output, asset.map(|a| a.symbol.clone()).unwrap_or_default())
+                    }
+                    _ => Self::read(output, asset.map(|a| a.decimals).unwrap_or_default()),
+                }
+            }
+            0x2000F => {
+                let (origin, id, owner) = Decode::decode(&mut input).unwrap();
+                Self::status(output, state.transfer_ownership(origin, id, owner))
+            }
+            _ => unreachable!("unknown pallet-assets function"),
+        }
+    }
+}
@@ -4,9 +4,19 @@ use ink::env::{DefaultEnvironment, Environment};
 
 use scale::{Decode, Encode, HasCompact};
 
+pub mod rewards;
+
+#[cfg(feature = "std")]
+pub mod mock;
+
 type Balance = <DefaultEnvironment as Environment>::Balance;
 type AccountId = <DefaultEnvironment as Environment>::AccountId;
 
+/// Reward destination paying claimed staker rewards to the free balance
+pub const REWARD_DESTINATION_FREE_BALANCE: u8 = 0;
+/// Reward destination restaking claimed staker rewards on the claimed contract
+pub const REWARD_DESTINATION_STAKE_BALANCE: u8 = 1;
+
 pub struct DappsStaking;
 
 impl DappsStaking {
@@ -122,6 +132,40 @@ impl DappsStaking {
             .call(&input)
     }
 
+    /// Claim staker rewards of up to `max_eras` ended eras, oldest first.
+    /// Stops once every ended era is claimed and returns the number of claimed eras.
+    pub fn claim_staker_all(contract: AccountId, max_eras: u32) -> Result<u32, DSError> {
+        // there are never more unclaimed eras than ended ones
+        let max_eras = max_eras.min(Self::read_current_era());
+        let mut claimed = 0;
+        while claimed < max_eras {
+            match Self::claim_staker(contract) {
+                Ok(()) => claimed += 1,
+                Err(DSError::EraOutOfBounds) | Err(DSError::AlreadyClaimedInThisEra) => break,
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(claimed)
+    }
+
+    /// Claim dapp rewards for every era in `from..to` that has ended.
+    /// Eras already claimed, without stake or out of the pallet history are skipped.
+    /// Returns the number of claimed eras.
+    pub fn claim_dapp_range(contract: AccountId, from: u32, to: u32) -> Result<u32, DSError> {
+        let to = to.min(Self::read_current_era());
+        let mut claimed = 0;
+        for era in from..to {
+            match Self::claim_dapp(contract, era) {
+                Ok(()) => claimed += 1,
+                Err(DSError::EraOutOfBounds)
+                | Err(DSError::AlreadyClaimedInThisEra)
+                | Err(DSError::NotStakedContract) => continue,
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(claimed)
+    }
+
     /// Set claim reward destination for the caller
     pub fn set_reward_destination(destination: u8) -> Result<(), DSError> {
         ::ink::env::chain_extension::ChainExtensionMethod::build(0013u32)
@@ -201,6 +245,20 @@ pub struct EraInfo<Balance: HasCompact> {
     pub locked: Balance,
 }
 
+/// A record of the total stake on a contract for an era
+#[derive(PartialEq, Debug, Eq, Clone, Default, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+pub struct ContractStakeInfo<Balance: HasCompact> {
+    /// Total staked amount on the contract
+    #[codec(compact)]
+    pub total: Balance,
+    /// Total number of active stakers
+    #[codec(compact)]
+    pub number_of_stakers: u32,
+    /// Indicates whether rewards were claimed for this era or not
+    pub contract_reward_claimed: bool,
+}
+
 #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
 #[derive(PartialEq, Eq, Copy, Clone, Encode, Decode, Debug)]
 pub enum DSError {
@@ -300,3 +358,90 @@ impl ink::env::chain_extension::FromStatusCode for DSError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::DappsStakingMock;
+
+    const CONTRACT: [u8; 32] = [0xC0; 32];
+    const DAPP: [u8; 32] = [0xDA; 32];
+
+    fn setup() -> DappsStakingMock {
+        let mock = DappsStakingMock::register(AccountId::from(CONTRACT));
+        mock.register_dapp(AccountId::from(DAPP));
+        DappsStaking::bond_and_stake(AccountId::from(DAPP), 100).unwrap();
+        mock
+    }
+
+    #[ink::test]
+    fn claim_staker_all_claims_every_ended_era() {
+        let mock = setup();
+        for era in 0..3 {
+            mock.set_era_rewards(era, 10, 0);
+            mock.advance_era();
+        }
+
+        assert_eq!(
+            DappsStaking::claim_staker_all(AccountId::from(DAPP), 10),
+            Ok(3)
+        );
+        assert_eq!(
+            DappsStaking::claim_staker_all(AccountId::from(DAPP), 10),
+            Ok(0)
+        );
+        assert_eq!(
+            mock.staked(AccountId::from(CONTRACT), AccountId::from(DAPP)),
+            130
+        );
+    }
+
+    #[ink::test]
+    fn claim_staker_all_respects_max_eras() {
+        let mock = setup();
+        for _ in 0..3 {
+            mock.advance_era();
+        }
+
+        assert_eq!(
+            DappsStaking::claim_staker_all(AccountId::from(DAPP), 2),
+            Ok(2)
+        );
+        assert_eq!(
+            DappsStaking::claim_staker_all(AccountId::from(DAPP), 2),
+            Ok(1)
+        );
+    }
+
+    #[ink::test]
+    fn claim_staker_all_fails_when_not_staked() {
+        let mock = DappsStakingMock::register(AccountId::from(CONTRACT));
+        mock.advance_era();
+
+        assert_eq!(
+            DappsStaking::claim_staker_all(AccountId::from(DAPP), 10),
+            Err(DSError::NotStakedContract)
+        );
+    }
+
+    #[ink::test]
+    fn claim_dapp_range_skips_claimed_eras() {
+        let mock = setup();
+        for era in 0..4 {
+            mock.set_era_rewards(era, 0, 10);
+            mock.advance_era();
+        }
+        DappsStaking::claim_dapp(AccountId::from(DAPP), 1).unwrap();
+
+        // era 5 has not ended yet
+        assert_eq!(
+            DappsStaking::claim_dapp_range(AccountId::from(DAPP), 0, 6),
+            Ok(3)
+        );
+        assert_eq!(
+            DappsStaking::claim_dapp_range(AccountId::from(DAPP), 0, 6),
+            Ok(0)
+        );
+        assert_eq!(mock.free_balance(AccountId::from(DAPP)), 40);
+    }
+}
@@ -0,0 +1,458 @@
+//! Off-chain mock of the dapps-staking chain extension.
+//!
+//! Register it at the start of an `#[ink::test]` to exercise contracts built on
+//! [`DappsStaking`](crate::DappsStaking) without a running node. All calls are
+//! executed as `origin`, which should be the account id of the contract under test.
+
+use crate::{
+    rewards, ContractStakeInfo, DSError, DappsStakingAccountInput, DappsStakingEraInput,
+    DappsStakingNominationInput, DappsStakingValueInput, EraInfo, RewardInfo,
+    REWARD_DESTINATION_STAKE_BALANCE,
+};
+use ink::env::test::ChainExtension;
+use ink::env::{DefaultEnvironment, Environment};
+use scale::{Decode, Encode};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+type Balance = <DefaultEnvironment as Environment>::Balance;
+type AccountId = <DefaultEnvironment as Environment>::AccountId;
+
+#[derive(Default)]
+struct State {
+    origin: Option<AccountId>,
+    current_era: u32,
+    unbonding_period: u32,
+    minimum_stake: Balance,
+    max_stakers: Option<u32>,
+    registered: BTreeSet<AccountId>,
+    /// Active stake per (staker, contract)
+    stakes: BTreeMap<(AccountId, AccountId), Balance>,
+    /// Unlocking chunks per staker as (amount, unlock era)
+    unlocking: BTreeMap<AccountId, Vec<(Balance, u32)>>,
+    /// Stakes as they were when the era ended
+    snapshots: BTreeMap<u32, BTreeMap<(AccountId, AccountId), Balance>>,
+    rewards: BTreeMap<u32, RewardInfo<Balance>>,
+    next_claim: BTreeMap<(AccountId, AccountId), u32>,
+    dapp_claimed: BTreeSet<(AccountId, u32)>,
+    destination: BTreeMap<AccountId, u8>,
+    free_balance: BTreeMap<AccountId, Balance>,
+}
+
+impl State {
+    fn origin(&self) -> AccountId {
+        self.origin.expect("mock origin must be set")
+    }
+
+    fn stake_of(&self, staker: AccountId, contract: AccountId) -> Balance {
+        self.stakes
+            .get(&(staker, contract))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn contract_stake(&self, contract: AccountId) -> Balance {
+        self.stakes
+            .iter()
+            .filter(|((_, c), _)| *c == contract)
+            .map(|(_, v)| v)
+            .sum()
+    }
+
+    fn stakers_count(&self, contract: AccountId) -> u32 {
+        self.stakes
+            .iter()
+            .filter(|((_, c), v)| *c == contract && **v > 0)
+            .count() as u32
+    }
+
+    fn ledger(&self, staker: AccountId) -> Balance {
+        let staked: Balance = self
+            .stakes
+            .iter()
+            .filter(|((s, _), _)| *s == staker)
+            .map(|(_, v)| v)
+            .sum();
+        let unlocking: Balance = self
+            .unlocking
+            .get(&staker)
+            .map(|chunks| chunks.iter().map(|(v, _)| v).sum())
+            .unwrap_or_default();
+        staked + unlocking
+    }
+
+    fn era_stakes(&self, era: u32) -> Option<&BTreeMap<(AccountId, AccountId), Balance>> {
+        if era >= self.current_era {
+            Some(&self.stakes)
+        } else {
+            self.snapshots.get(&era)
+        }
+    }
+
+    fn era_staked(&self, era: u32) -> Balance {
+        self.era_stakes(era)
+            .map(|stakes| stakes.values().sum())
+            .unwrap_or_default()
+    }
+
+    fn era_info(&self, era: u32) -> EraInfo<Balance> {
+        let staked = self.era_staked(era);
+        EraInfo {
+            rewards: self.rewards.get(&era).cloned().unwrap_or_default(),
+            staked,
+            locked: staked,
+        }
+    }
+
+    fn contract_stake_info(&self, era: u32, contract: AccountId) -> ContractStakeInfo<Balance> {
+        let stakes = self.era_stakes(era);
+        let contract_stakes = stakes
+            .iter()
+            .flat_map(|stakes| stakes.iter())
+            .filter(|((_, c), v)| *c == contract && **v > 0);
+        ContractStakeInfo {
+            total: contract_stakes.clone().map(|(_, v)| v).sum(),
+            number_of_stakers: contract_stakes.count() as u32,
+            contract_reward_claimed: self.dapp_claimed.contains(&(contract, era)),
+        }
+    }
+
+    fn credit(&mut self, who: AccountId, amount: Balance) {
+        *self.free_balance.entry(who).or_default() += amount;
+    }
+
+    fn add_stake(
+        &mut self,
+        staker: AccountId,
+        contract: AccountId,
+        value: Balance,
+    ) -> Result<(), DSError> {
+        let current = self.stake_of(staker, contract);
+        if current + value < self.minimum_stake {
+            return Err(DSError::InsufficientValue);
+        }
+        if current == 0 {
+            if let Some(max) = self.max_stakers {
+                if self.stakers_count(contract) >= max {
+                    return Err(DSError::MaxNumberOfStakersExceeded);
+                }
+            }
+            self.next_claim
+                .entry((staker, contract))
+                .or_insert(self.current_era);
+        }
+        self.stakes.insert((staker, contract), current + value);
+        Ok(())
+    }
+
+    /// Removes up to `value` from the stake, unstaking everything if the remainder
+    /// would fall below the minimum. Returns the amount actually removed.
+    fn remove_stake(&mut self, staker: AccountId, contract: AccountId, value: Balance) -> Balance {
+        let current = self.stake_of(staker, contract);
+        let remaining = current.saturating_sub(value);
+        let removed = if remaining < self.minimum_stake {
+            current
+        } else {
+            value.min(current)
+        };
+        self.stakes.insert((staker, contract), current - removed);
+        removed
+    }
+
+    fn bond_and_stake(&mut self, input: DappsStakingValueInput) -> Result<(), DSError> {
+        if !self.registered.contains(&input.contract) {
+            return Err(DSError::NotOperatedContract);
+        }
+        if input.value == 0 {
+            return Err(DSError::StakingWithNoValue);
+        }
+        let origin = self.origin();
+        self.add_stake(origin, input.contract, input.value)
+    }
+
+    fn unbond_and_unstake(&mut self, input: DappsStakingValueInput) -> Result<(), DSError> {
+        if input.value == 0 {
+            return Err(DSError::UnstakingWithNoValue);
+        }
+        let origin = self.origin();
+        if self.stake_of(origin, input.contract) == 0 {
+            return Err(DSError::NotStakedContract);
+        }
+        let removed = self.remove_stake(origin, input.contract, input.value);
+        let unlock_era = self.current_era + self.unbonding_period;
+        self.unlocking
+            .entry(origin)
+            .or_default()
+            .push((removed, unlock_era));
+        Ok(())
+    }
+
+    fn withdraw_unbonded(&mut self) -> Result<(), DSError> {
+        let origin = self.origin();
+        let current_era = self.current_era;
+        let chunks = self.unlocking.entry(origin).or_default();
+        let withdrawn: Balance = chunks
+            .iter()
+            .filter(|(_, era)| *era <= current_era)
+            .map(|(v, _)| v)
+            .sum();
+        if withdrawn == 0 {
+            return Err(DSError::NothingToWithdraw);
+        }
+        chunks.retain(|(_, era)| *era > current_era);
+        self.credit(origin, withdrawn);
+        Ok(())
+    }
+
+    fn claim_staker(&mut self, contract: AccountId) -> Result<(), DSError> {
+        let origin = self.origin();
+        let era = *self
+            .next_claim
+            .get(&(origin, contract))
+            .ok_or(DSError::NotStakedContract)?;
+        if era >= self.current_era {
+            return Err(DSError::EraOutOfBounds);
+        }
+        let staked = self
+            .era_stakes(era)
+            .and_then(|stakes| stakes.get(&(origin, contract)).copied())
+            .unwrap_or_default();
+        let reward = if staked == 0 {
+            0
+        } else {
+            rewards::staker_reward(
+                staked,
+                &self.contract_stake_info(era, contract),
+                &self.era_info(era),
+            )
+        };
+        self.next_claim.insert((origin, contract), era + 1);
+
+        let restake = self
+            .destination
+            .get(&origin)
+            .copied()
+            .unwrap_or(REWARD_DESTINATION_STAKE_BALANCE)
+            == REWARD_DESTINATION_STAKE_BALANCE;
+        if restake && self.stake_of(origin, contract) > 0 {
+            *self.stakes.entry((origin, contract)).or_default() += reward;
+        } else {
+            self.credit(origin, reward);
+        }
+        Ok(())
+    }
+
+    fn claim_dapp(&mut self, input: DappsStakingEraInput) -> Result<(), DSError> {
+        if !self.registered.contains(&input.contract) {
+            return Err(DSError::NotOperatedContract);
+        }
+        if input.era >= self.current_era {
+            return Err(DSError::EraOutOfBounds);
+        }
+        if self.dapp_claimed.contains(&(input.contract, input.era)) {
+            return Err(DSError::AlreadyClaimedInThisEra);
+        }
+        let contract_stake_info = self.contract_stake_info(input.era, input.contract);
+        if contract_stake_info.total == 0 {
+            return Err(DSError::NotStakedContract);
+        }
+        let reward = rewards::dapp_reward(&contract_stake_info, &self.era_info(input.era));
+        self.dapp_claimed.insert((input.contract, input.era));
+        self.credit(input.contract, reward);
+        Ok(())
+    }
+
+    fn set_reward_destination(&mut self, destination: u8) -> Result<(), DSError> {
+        if destination > REWARD_DESTINATION_STAKE_BALANCE {
+            return Err(DSError::RewardDestinationValueOutOfBounds);
+        }
+        let origin = self.origin();
+        if self.ledger(origin) == 0 {
+            return Err(DSError::NotActiveStaker);
+        }
+        self.destination.insert(origin, destination);
+        Ok(())
+    }
+
+    fn nomination_transfer(&mut self, input: DappsStakingNominationInput) -> Result<(), DSError> {
+        if input.origin_contract == input.target_contract {
+            return Err(DSError::NominationTransferToSameContract);
+        }
+        if !self.registered.contains(&input.target_contract) {
+            return Err(DSError::NotOperatedContract);
+        }
+        if input.value == 0 {
+            return Err(DSError::UnstakingWithNoValue);
+        }
+        let origin = self.origin();
+        if self.stake_of(origin, input.origin_contract) == 0 {
+            return Err(DSError::NotStakedContract);
+        }
+        let current = self.stake_of(origin, input.origin_contract);
+        let moved = if current.saturating_sub(input.value) < self.minimum_stake {
+            current
+        } else {
+            input.value.min(current)
+        };
+        self.add_stake(origin, input.target_contract, moved)?;
+        self.remove_stake(origin, input.origin_contract, moved);
+        Ok(())
+    }
+}
+
+/// Handle to the mocked dapps-staking pallet state.
+///
+/// Cloning the handle shares the same state, so a test can keep one to drive eras
+/// and inspect balances while the registered extension methods serve the contract.
+#[derive(Clone, Default)]
+pub struct DappsStakingMock {
+    state: Rc<RefCell<State>>,
+}
+
+impl DappsStakingMock {
+    /// Registers every dapps-staking method in the off-chain environment,
+    /// calling them as `origin`.
+    pub fn register(origin: AccountId) -> Self {
+        let mock = Self::default();
+        mock.state.borrow_mut().origin = Some(origin);
+        for func_id in 1..=14 {
+            ink::env::test::register_chain_extension(Method {
+                func_id,
+                state: mock.state.clone(),
+            });
+        }
+        mock
+    }
+
+    /// Changes the account the extension methods are called as.
+    pub fn set_origin(&self, origin: AccountId) {
+        self.state.borrow_mut().origin = Some(origin);
+    }
+
+    /// Registers `contract` so it can be staked on.
+    pub fn register_dapp(&self, contract: AccountId) {
+        self.state.borrow_mut().registered.insert(contract);
+    }
+
+    pub fn set_unbonding_period(&self, eras: u32) {
+        self.state.borrow_mut().unbonding_period = eras;
+    }
+
+    pub fn set_minimum_stake(&self, value: Balance) {
+        self.state.borrow_mut().minimum_stake = value;
+    }
+
+    pub fn set_max_stakers(&self, max: u32) {
+        self.state.borrow_mut().max_stakers = Some(max);
+    }
+
+    /// Sets the rewards distributed for `era`.
+    pub fn set_era_rewards(&self, era: u32, stakers: Balance, dapps: Balance) {
+        self.state
+            .borrow_mut()
+            .rewards
+            .insert(era, RewardInfo { stakers, dapps });
+    }
+
+    /// Ends the current era, snapshotting stakes for reward calculation.
+    pub fn advance_era(&self) {
+        let mut state = self.state.borrow_mut();
+        let era = state.current_era;
+        let snapshot = state.stakes.clone();
+        state.snapshots.insert(era, snapshot);
+        state.current_era += 1;
+    }
+
+    pub fn current_era(&self) -> u32 {
+        self.state.borrow().current_era
+    }
+
+    /// Stake of an external account on `contract`, bypassing the origin.
+    pub fn stake_as(
+        &self,
+        staker: AccountId,
+        contract: AccountId,
+        value: Balance,
+    ) -> Result<(), DSError> {
+        self.state.borrow_mut().add_stake(staker, contract, value)
+    }
+
+    pub fn staked(&self, staker: AccountId, contract: AccountId) -> Balance {
+        self.state.borrow().stake_of(staker, contract)
+    }
+
+    /// Balance paid out to `who` by claims and withdrawals.
+    pub fn free_balance(&self, who: AccountId) -> Balance {
+        self.state
+            .borrow()
+            .free_balance
+            .get(&who)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+struct Method {
+    func_id: u32,
+    state: Rc<RefCell<State>>,
+}
+
+impl Method {
+    fn read<T: Encode>(output: &mut Vec<u8>, value: T) -> u32 {
+        value.encode_to(output);
+        0
+    }
+
+    fn status(result: Result<(), DSError>) -> u32 {
+        match result {
+            Ok(()) => 0,
+            Err(e) => e as u32,
+        }
+    }
+}
+
+impl ChainExtension for Method {
+    fn func_id(&self) -> u32 {
+        self.func_id
+    }
+
+    fn call(&mut self, input: &[u8], output: &mut Vec<u8>) -> u32 {
+        let input = Vec::<u8>::decode(&mut &input[..]).expect("input is a byte vector");
+        let mut input = &input[..];
+        let mut state = self.state.borrow_mut();
+        match self.func_id {
+            1 => Self::read(output, state.current_era),
+            2 => Self::read(output, state.unbonding_period),
+            3 => {
+                let era = u32::decode(&mut input).unwrap();
+                let rewards = state.rewards.get(&era).cloned().unwrap_or_default();
+                Self::read(output, rewards.stakers + rewards.dapps)
+            }
+            4 => {
+                let era = u32::decode(&mut input).unwrap();
+                Self::read(output, state.era_staked(era))
+            }
+            5 => {
+                let account = AccountId::decode(&mut input).unwrap();
+                Self::read(output, state.ledger(account))
+            }
+            6 => {
+                let input = DappsStakingAccountInput::decode(&mut input).unwrap();
+                Self::read(output, state.stake_of(input.staker, input.contract))
+            }
+            7 => {
+                let contract = AccountId::decode(&mut input).unwrap();
+                Self::read(output, state.contract_stake(contract))
+            }
+            8 => Self::status(state.bond_and_stake(Decode::decode(&mut input).unwrap())),
+            9 => Self::status(state.unbond_and_unstake(Decode::decode(&mut input).unwrap())),
+            10 => Self::status(state.withdraw_unbonded()),
+            11 => Self::status(state.claim_staker(Decode::decode(&mut input).unwrap())),
+            12 => Self::status(state.claim_dapp(Decode::decode(&mut input).unwrap())),
+            13 => Self::status(state.set_reward_destination(Decode::decode(&mut input).unwrap())),
+            14 => Self::status(state.nomination_transfer(Decode::decode(&mut input).unwrap())),
+            _ => unreachable!("unknown dapps-staking function"),
+        }
+    }
+}
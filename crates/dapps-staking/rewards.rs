@@ -0,0 +1,170 @@
+//! Reward calculations of the dapps-staking pallet.
+//!
+//! Mirrors the arithmetic the pallet uses when `claim_staker` and `claim_dapp` are called,
+//! so contracts and front-ends can estimate claimable rewards without reimplementing it.
+
+use crate::{ContractStakeInfo, EraInfo};
+use ink::env::{DefaultEnvironment, Environment};
+use sp_arithmetic::{FixedPointNumber, FixedU128, Perbill};
+
+type Balance = <DefaultEnvironment as Environment>::Balance;
+
+/// Split of the era rewards allocated to `contract` into the dapp reward
+/// and the joint reward of all its stakers.
+pub fn dev_stakers_split(
+    contract: &ContractStakeInfo<Balance>,
+    era: &EraInfo<Balance>,
+) -> (Balance, Balance) {
+    let contract_stake_portion = Perbill::from_rational(contract.total, era.staked);
+    let developer_reward_part = contract_stake_portion * era.rewards.dapps;
+    let stakers_joint_reward = contract_stake_portion * era.rewards.stakers;
+    (developer_reward_part, stakers_joint_reward)
+}
+
+/// Reward paid to the developer of `contract` by `claim_dapp` for the era.
+pub fn dapp_reward(contract: &ContractStakeInfo<Balance>, era: &EraInfo<Balance>) -> Balance {
+    dev_stakers_split(contract, era).0
+}
+
+/// Reward paid to a staker who had `staked` on `contract` during the era by `claim_staker`.
+pub fn staker_reward(
+    staked: Balance,
+    contract: &ContractStakeInfo<Balance>,
+    era: &EraInfo<Balance>,
+) -> Balance {
+    let (_, stakers_joint_reward) = dev_stakers_split(contract, era);
+    Perbill::from_rational(staked, contract.total) * stakers_joint_reward
+}
+
+/// Estimated yearly staker reward per staked unit, assuming every era
+/// pays the same rewards for the same total stake as `era`.
+/// Returns `None` when nothing is staked.
+pub fn estimate_staker_apr(era: &EraInfo<Balance>, eras_per_year: u32) -> Option<FixedU128> {
+    let yearly_rewards = era.rewards.stakers.checked_mul(eras_per_year.into())?;
+    FixedU128::checked_from_rational(yearly_rewards, era.staked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RewardInfo;
+    use proptest::prelude::*;
+
+    const ONE: Balance = 1_000_000_000_000_000_000;
+
+    fn era(stakers: Balance, dapps: Balance, staked: Balance) -> EraInfo<Balance> {
+        EraInfo {
+            rewards: RewardInfo { stakers, dapps },
+            staked,
+            locked: staked,
+        }
+    }
+
+    fn contract(total: Balance) -> ContractStakeInfo<Balance> {
+        ContractStakeInfo {
+            total,
+            number_of_stakers: 1,
+            contract_reward_claimed: false,
+        }
+    }
+
+    #[test]
+    fn known_vectors() {
+        // (era stakers reward, era dapps reward, era staked, contract total, staked,
+        //  expected dapp reward, expected staker reward)
+        let vectors: [(
+            Balance,
+            Balance,
+            Balance,
+            Balance,
+            Balance,
+            Balance,
+            Balance,
+        ); 6] = [
+            (1_000, 500, 3_000, 1_000, 250, 167, 83),
+            (1_000, 500, 3_000, 3_000, 3_000, 500, 1_000),
+            (1_000, 500, 3_000, 0, 0, 0, 0),
+            (7, 3, 9, 4, 1, 1, 1),
+            (
+                253_611 * ONE,
+                126_805 * ONE,
+                1_572_431_001 * ONE,
+                51_203_775 * ONE,
+                1_250 * ONE,
+                4_129_207_896_835_000_000_000,
+                201_605_241_293_940_204,
+            ),
+            (
+                1_332 * ONE / 10,
+                666 * ONE / 10,
+                10_000_000 * ONE + 7,
+                3_333_333 * ONE,
+                333_333 * ONE + 3,
+                22_199_997_713_400_000_000,
+                4_439_995_502_280_416_161,
+            ),
+        ];
+
+        for (stakers, dapps, staked, total, staker, dapp_expected, staker_expected) in vectors {
+            let era = era(stakers, dapps, staked);
+            let contract = contract(total);
+            assert_eq!(dapp_reward(&contract, &era), dapp_expected);
+            assert_eq!(staker_reward(staker, &contract, &era), staker_expected);
+        }
+    }
+
+    #[test]
+    fn apr_is_annualized_stakers_reward() {
+        let era = era(10 * ONE, 5 * ONE, 1_000 * ONE);
+        assert_eq!(
+            estimate_staker_apr(&era, 365),
+            Some(FixedU128::from_rational(365, 100))
+        );
+        assert_eq!(estimate_staker_apr(&self::era(10, 5, 0), 365), None);
+    }
+
+    proptest! {
+        #[test]
+        fn split_never_exceeds_era_rewards(
+            stakers in 0..u64::MAX as Balance * ONE,
+            dapps in 0..u64::MAX as Balance * ONE,
+            staked in 1..u64::MAX as Balance * ONE,
+            total in 0..u64::MAX as Balance * ONE,
+        ) {
+            let total = total % (staked + 1);
+            let (dapp, joint) = dev_stakers_split(&contract(total), &era(stakers, dapps, staked));
+            prop_assert!(dapp <= dapps);
+            prop_assert!(joint <= stakers);
+        }
+
+        #[test]
+        fn stakers_never_receive_more_than_joint_reward(
+            stakers in 0..u64::MAX as Balance * ONE,
+            staked in 1..u64::MAX as Balance * ONE,
+            stakes in prop::collection::vec(0..u32::MAX as Balance * ONE, 1..16),
+        ) {
+            let total: Balance = stakes.iter().sum();
+            let staked = staked.max(total);
+            let era = era(stakers, 0, staked);
+            let contract = contract(total);
+            let (_, joint) = dev_stakers_split(&contract, &era);
+            // rounding to nearest may hand out up to one unit per staker too much
+            let paid: Balance = stakes.iter().map(|s| staker_reward(*s, &contract, &era)).sum();
+            prop_assert!(paid <= joint + stakes.len() as Balance);
+        }
+
+        #[test]
+        fn staker_reward_grows_with_stake(
+            stakers in 0..u64::MAX as Balance * ONE,
+            total in 1..u64::MAX as Balance * ONE,
+            a in 0..u64::MAX as Balance * ONE,
+            b in 0..u64::MAX as Balance * ONE,
+        ) {
+            let (a, b) = (a % (total + 1), b % (total + 1));
+            let (low, high) = (a.min(b), a.max(b));
+            let era = era(stakers, 0, total);
+            let contract = contract(total);
+            prop_assert!(staker_reward(low, &contract, &era) <= staker_reward(high, &contract, &era));
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Account which signed a message with an ecdsa key.
+//!
+//! Like substrate ecdsa signers, the message is hashed with blake2 before
+//! signing and the account of a key is the blake2 hash of its compressed public
+//! key, so accounts of ecdsa keys can be checked against `AccountId`s.
+//!
+//! With the `signing` feature, [`sign`] and [`account`] produce signatures and
+//! accounts off-chain, e.g. in unit tests.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink::env::hash::{Blake2x256, HashOutput};
+use ink::primitives::AccountId;
+
+#[cfg(any(test, feature = "signing"))]
+pub use secp256k1::SecretKey;
+#[cfg(any(test, feature = "signing"))]
+use secp256k1::{Message, SECP256K1};
+
+pub fn blake2_256(input: &[u8]) -> [u8; 32] {
+    let mut output = <Blake2x256 as HashOutput>::Type::default();
+    ink::env::hash_bytes::<Blake2x256>(input, &mut output);
+    output
+}
+
+/// Account of the ecdsa key which signed `message`, `None` if the signature
+/// is invalid
+pub fn signer(message: &[u8], signature: &[u8; 65]) -> Option<AccountId> {
+    let mut public_key = [0; 33];
+    ink::env::ecdsa_recover(signature, &blake2_256(message), &mut public_key).ok()?;
+    Some(AccountId::from(blake2_256(&public_key)))
+}
+
+/// Account of the ecdsa key `secret`
+#[cfg(any(test, feature = "signing"))]
+pub fn account(secret: &SecretKey) -> AccountId {
+    AccountId::from(blake2_256(&secret.public_key(SECP256K1).serialize()))
+}
+
+/// Recoverable signature of `message` by `secret`, as checked by [`signer`]
+#[cfg(any(test, feature = "signing"))]
+pub fn sign(secret: &SecretKey, message: &[u8]) -> [u8; 65] {
+    let hash = Message::from_slice(&blake2_256(message)).expect("hash is 32 bytes");
+    let (recovery_id, compact) = SECP256K1
+        .sign_ecdsa_recoverable(&hash, secret)
+        .serialize_compact();
+    let mut signature = [0; 65];
+    signature[..64].copy_from_slice(&compact);
+    signature[64] = recovery_id.to_i32() as u8;
+    signature
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_signer() {
+        let secret = SecretKey::from_slice(&[0x11; 32]).unwrap();
+        let signature = sign(&secret, b"message");
+
+        assert_eq!(signer(b"message", &signature), Some(account(&secret)));
+        assert_ne!(signer(b"other message", &signature), Some(account(&secret)));
+        assert_eq!(signer(b"message", &[0; 65]), None);
+    }
+}
@@ -0,0 +1,208 @@
+//! Merkle tree of `(account, amount)` entries, e.g. airdrop recipients or
+//! whitelisted buyers with their allowance.
+//!
+//! Leaf `i` is the blake2 hash of the SCALE encoded `(i as u32, account, amount)`
+//! and every node is the blake2 hash of its two children in ascending order, so
+//! proofs are plain lists of siblings. A node without sibling is carried up
+//! unchanged to the next layer.
+//!
+//! With the `std` feature, [`parse_recipients`] and [`MerkleTree`] build the
+//! root given to a contract and the proof of each recipient.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use ink::env::hash::{Blake2x256, HashOutput};
+use ink::primitives::AccountId;
+use scale::Encode;
+
+type Balance = u128;
+
+pub fn leaf_hash(index: u32, account: &AccountId, amount: Balance) -> [u8; 32] {
+    blake2_256(&(index, account, amount).encode())
+}
+
+pub fn node_hash(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut input = [0; 64];
+    input[..32].copy_from_slice(first);
+    input[32..].copy_from_slice(second);
+    blake2_256(&input)
+}
+
+/// Whether `proof` leads from `leaf` to `root`
+pub fn verify(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| node_hash(&node, sibling))
+        == *root
+}
+
+fn blake2_256(input: &[u8]) -> [u8; 32] {
+    let mut output = <Blake2x256 as HashOutput>::Type::default();
+    ink::env::hash_bytes::<Blake2x256>(input, &mut output);
+    output
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recipient {
+    pub account: AccountId,
+    pub amount: Balance,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line number
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+/// Parses one `account,amount` recipient per line, the account being 32 bytes
+/// in hex with an optional `0x` prefix. Blank lines and lines starting with `#`
+/// are skipped.
+#[cfg(feature = "std")]
+pub fn parse_recipients(input: &str) -> Result<Vec<Recipient>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line, content)| {
+            let error = |reason| ParseError { line, reason };
+            let (account, amount) = content
+                .split_once(',')
+                .ok_or_else(|| error("expected `account,amount`"))?;
+            Ok(Recipient {
+                account: parse_account(account.trim()).ok_or_else(|| error("invalid account"))?,
+                amount: amount.trim().parse().map_err(|_| error("invalid amount"))?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(feature = "std")]
+fn parse_account(hex: &str) -> Option<AccountId> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if hex.len() != 64 || !hex.is_ascii() {
+        return None;
+    }
+    let mut account = [0; 32];
+    for (byte, digits) in account.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()?;
+    }
+    Some(AccountId::from(account))
+}
+
+/// All layers of the tree, from the leaves up to the root
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+#[cfg(feature = "std")]
+impl MerkleTree {
+    /// Tree with recipient `i` at leaf index `i`
+    pub fn new(recipients: &[Recipient]) -> Self {
+        let mut layer: Vec<_> = recipients
+            .iter()
+            .enumerate()
+            .map(|(index, recipient)| leaf_hash(index as u32, &recipient.account, recipient.amount))
+            .collect();
+        let mut layers = Vec::new();
+        while layer.len() > 1 {
+            let next = layer
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => node_hash(a, b),
+                    [node] => *node,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(std::mem::replace(&mut layer, next));
+        }
+        layers.push(layer);
+        Self { layers }
+    }
+
+    /// Zero hash for an empty tree, which no proof matches
+    pub fn root(&self) -> [u8; 32] {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Siblings of leaf `index` from the bottom up, `None` if out of range
+    pub fn proof(&self, index: u32) -> Option<Vec<[u8; 32]>> {
+        let mut index = index as usize;
+        if index >= self.layers[0].len() {
+            return None;
+        }
+        let mut proof = Vec::new();
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_recipients() {
+        let input = "# account,amount\n\
+            0x0101010101010101010101010101010101010101010101010101010101010101,100\n\
+            \n\
+            0202020202020202020202020202020202020202020202020202020202020202, 7\n";
+        assert_eq!(
+            parse_recipients(input),
+            Ok(vec![
+                Recipient {
+                    account: AccountId::from([0x01; 32]),
+                    amount: 100,
+                },
+                Recipient {
+                    account: AccountId::from([0x02; 32]),
+                    amount: 7,
+                },
+            ])
+        );
+        assert_eq!(
+            parse_recipients("0x01,100").unwrap_err(),
+            ParseError {
+                line: 1,
+                reason: "invalid account",
+            }
+        );
+        assert_eq!(parse_recipients("\n\nfoo").unwrap_err().line, 3);
+    }
+
+    #[test]
+    fn every_proof_verifies() {
+        for count in 1..=7u8 {
+            let recipients: Vec<_> = (0..count)
+                .map(|i| Recipient {
+                    account: AccountId::from([i; 32]),
+                    amount: i as Balance * 10,
+                })
+                .collect();
+            let tree = MerkleTree::new(&recipients);
+            for (index, recipient) in recipients.iter().enumerate() {
+                let leaf = leaf_hash(index as u32, &recipient.account, recipient.amount);
+                let proof = tree.proof(index as u32).unwrap();
+                assert!(verify(&tree.root(), leaf, &proof));
+                let wrong = leaf_hash(index as u32, &recipient.account, recipient.amount + 1);
+                assert!(!verify(&tree.root(), wrong, &proof));
+            }
+            assert_eq!(tree.proof(count as u32), None);
+        }
+    }
+}
@@ -5,10 +5,23 @@ use ink::prelude::vec::Vec;
 use ink::env::{DefaultEnvironment, Environment};
 use scale::{Decode, Encode};
 
+#[cfg(feature = "std")]
+pub mod mock;
+
 type Balance = <DefaultEnvironment as Environment>::Balance;
 type AccountId = <DefaultEnvironment as Environment>::AccountId;
 type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
 
+/// Gas limit `(ref_time, proof_size)` of a scheduled contract call. `ref_time`
+/// is in picoseconds, 0.65 s of execution which fits in [`CALL_MAX_WEIGHT`], and
+/// `proof_size` in bytes. It is a generous bound for a single message rather
+/// than a measured cost, lower it for heavy runtimes.
+pub const CALL_GAS_LIMIT: (u64, u64) = (649_901_026_000, 629_760);
+
+/// Maximum weight the scheduler may spend dispatching a contract call, 1 s of
+/// `ref_time`
+pub const CALL_MAX_WEIGHT: u64 = 1_000_000_000_000;
+
 pub struct SchedulerExtension;
 
 impl SchedulerExtension {
@@ -54,7 +67,7 @@ pub enum SchedulerError {
     RescheduleNoChange = 4,
     /// Attempt to use a non-named function on a named task.
     Named = 5,
-    /// Origin Caller is not supported
+    /// The runtime does not support `Origin::Caller`
     OriginCannotBeCaller = 98,
     /// Unknown error
     RuntimeError = 99,
@@ -85,13 +98,18 @@ impl From<scale::Error> for SchedulerError {
     }
 }
 
-#[derive(Clone, Copy, Decode, Encode)]
+/// Account a chain extension call is dispatched as.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Decode, Encode)]
 #[cfg_attr(
     feature = "std",
     derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
 )]
 pub enum Origin {
+    /// The immediate caller of the contract, as returned by `env().caller()`.
+    /// When the contract is called by another contract this is the calling
+    /// contract, never the account which signed the transaction.
     Caller,
+    /// The contract itself, as returned by `env().account_id()`
     Address,
 }
 
@@ -101,7 +119,7 @@ impl Default for Origin {
     }
 }
 
-#[derive(Encode, Decode)]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
 pub struct ContractCallInput {
     pub dest: AccountId,
     pub data: Vec<u8>,
@@ -110,3 +128,82 @@ pub struct ContractCallInput {
     pub value: Balance,
     pub max_weight: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::SchedulerMock;
+    use ink::env::test;
+
+    const CONTRACT: [u8; 32] = [0xC0; 32];
+
+    fn call(data: u8) -> ContractCallInput {
+        ContractCallInput {
+            dest: AccountId::from(CONTRACT),
+            data: ink::prelude::vec![data],
+            gas_limit: (1_000_000_000, 100_000),
+            storage_deposit_limit: None,
+            value: 0,
+            max_weight: 1_000_000_000,
+        }
+    }
+
+    #[ink::test]
+    fn caller_origin_dispatches_as_caller() {
+        let accounts = test::default_accounts::<DefaultEnvironment>();
+        let mock = SchedulerMock::register(AccountId::from(CONTRACT));
+        mock.set_caller(accounts.bob);
+
+        SchedulerExtension::schedule(Origin::Caller, 2, None, 0, call(1)).unwrap();
+        SchedulerExtension::schedule(Origin::Address, 2, None, 0, call(2)).unwrap();
+
+        let due = mock.run_to_block(2);
+        assert_eq!(due.len(), 2);
+        assert_eq!(due[0].1.origin, accounts.bob);
+        assert_eq!(due[1].1.origin, AccountId::from(CONTRACT));
+        assert_eq!(ink::env::block_number::<DefaultEnvironment>(), 2);
+    }
+
+    #[ink::test]
+    fn only_scheduling_origin_can_cancel() {
+        let accounts = test::default_accounts::<DefaultEnvironment>();
+        let mock = SchedulerMock::register(AccountId::from(CONTRACT));
+        mock.set_caller(accounts.bob);
+        SchedulerExtension::schedule(Origin::Caller, 5, None, 0, call(1)).unwrap();
+
+        assert_eq!(
+            SchedulerExtension::cancel(Origin::Address, 5, 0),
+            Err(SchedulerError::RuntimeError)
+        );
+        assert_eq!(SchedulerExtension::cancel(Origin::Caller, 5, 0), Ok(()));
+        assert_eq!(
+            SchedulerExtension::cancel(Origin::Caller, 5, 0),
+            Err(SchedulerError::NotFound)
+        );
+        assert!(mock.run_to_block(5).is_empty());
+    }
+
+    #[ink::test]
+    fn periodic_task_runs_count_times() {
+        let mock = SchedulerMock::register(AccountId::from(CONTRACT));
+        SchedulerExtension::schedule(Origin::Address, 1, Some((3, 3)), 0, call(1)).unwrap();
+
+        let blocks: Vec<BlockNumber> = mock.run_to_block(20).into_iter().map(|(b, _)| b).collect();
+        assert_eq!(blocks, [1, 4, 7]);
+        assert_eq!(
+            SchedulerExtension::schedule(Origin::Address, 20, None, 0, call(1)),
+            Err(SchedulerError::TargetBlockNumberInPast)
+        );
+    }
+
+    #[ink::test]
+    fn runtime_without_caller_origin_fails() {
+        let mock = SchedulerMock::register(AccountId::from(CONTRACT));
+        mock.reject_caller_origin();
+
+        assert_eq!(
+            SchedulerExtension::schedule(Origin::Caller, 2, None, 0, call(1)),
+            Err(SchedulerError::OriginCannotBeCaller)
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Off-chain mock of the pallet-scheduler chain extension.
+//!
+//! Register it at the start of an `#[ink::test]` to exercise contracts built on
+//! [`SchedulerExtension`](crate::SchedulerExtension) without a running node.
+//! `Origin::Address` resolves to the contract given to [`SchedulerMock::register`],
+//! `Origin::Caller` to the account set with [`SchedulerMock::set_caller`].
+//!
+//! The off-chain environment cannot call contracts, so [`SchedulerMock::run_to_block`]
+//! returns the calls which became due and the test dispatches them itself, as the
+//! origin they were scheduled with.
+//!
+//! With the `assets` feature, [`Mocks`] registers it together with the
+//! pallet-assets mock for contracts built on both extensions.
+
+use crate::{ContractCallInput, Origin, SchedulerError};
+use ink::env::test::ChainExtension;
+use ink::env::{DefaultEnvironment, Environment};
+use scale::{Decode, Encode};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+type AccountId = <DefaultEnvironment as Environment>::AccountId;
+type BlockNumber = <DefaultEnvironment as Environment>::BlockNumber;
+
+/// Default maximum number of tasks per block
+pub const MAX_SCHEDULED_PER_BLOCK: usize = 50;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Task {
+    /// Account the call is dispatched as
+    pub origin: AccountId,
+    /// Period and number of remaining repetitions
+    pub maybe_periodic: Option<(BlockNumber, u32)>,
+    pub priority: u8,
+    pub call: ContractCallInput,
+}
+
+struct State {
+    contract: AccountId,
+    caller: AccountId,
+    caller_origin_supported: bool,
+    block: BlockNumber,
+    max_scheduled_per_block: usize,
+    agenda: BTreeMap<BlockNumber, Vec<Option<Task>>>,
+}
+
+impl State {
+    fn origin(&self, origin: Origin) -> Result<AccountId, SchedulerError> {
+        match origin {
+            Origin::Address => Ok(self.contract),
+            Origin::Caller if self.caller_origin_supported => Ok(self.caller),
+            Origin::Caller => Err(SchedulerError::OriginCannotBeCaller),
+        }
+    }
+
+    fn place(&mut self, when: BlockNumber, task: Task) -> Result<u32, SchedulerError> {
+        let max = self.max_scheduled_per_block;
+        let agenda = self.agenda.entry(when).or_default();
+        if agenda.len() < max {
+            agenda.push(Some(task));
+            return Ok(agenda.len() as u32 - 1);
+        }
+        let index = agenda
+            .iter()
+            .position(Option::is_none)
+            .ok_or(SchedulerError::FailedToSchedule)?;
+        agenda[index] = Some(task);
+        Ok(index as u32)
+    }
+
+    fn schedule(
+        &mut self,
+        origin: Origin,
+        when: BlockNumber,
+        maybe_periodic: Option<(BlockNumber, u32)>,
+        priority: u8,
+        call: ContractCallInput,
+    ) -> Result<(), SchedulerError> {
+        let origin = self.origin(origin)?;
+        if when <= self.block {
+            return Err(SchedulerError::TargetBlockNumberInPast);
+        }
+        // like the pallet, keep the number of repetitions after the first call
+        let maybe_periodic = maybe_periodic
+            .filter(|(period, count)| *period > 0 && *count > 1)
+            .map(|(period, count)| (period, count - 1));
+        self.place(
+            when,
+            Task {
+                origin,
+                maybe_periodic,
+                priority,
+                call,
+            },
+        )
+        .map(|_| ())
+    }
+
+    fn cancel(
+        &mut self,
+        origin: Origin,
+        when: BlockNumber,
+        index: u32,
+    ) -> Result<(), SchedulerError> {
+        let origin = self.origin(origin)?;
+        let slot = self
+            .agenda
+            .get_mut(&when)
+            .and_then(|agenda| agenda.get_mut(index as usize))
+            .filter(|slot| slot.is_some())
+            .ok_or(SchedulerError::NotFound)?;
+        // the pallet fails with `BadOrigin`, which is not a module error
+        if slot.as_ref().map(|task| task.origin) != Some(origin) {
+            return Err(SchedulerError::RuntimeError);
+        }
+        *slot = None;
+        Ok(())
+    }
+}
+
+/// Handle to the mocked pallet-scheduler state.
+///
+/// Cloning the handle shares the same state, so a test can keep one to advance
+/// blocks and inspect the agenda while the registered extension methods serve the contract.
+#[derive(Clone)]
+pub struct SchedulerMock {
+    state: Rc<RefCell<State>>,
+}
+
+impl SchedulerMock {
+    /// Registers every pallet-scheduler method in the off-chain environment.
+    /// `contract` becomes the callee of the test and the account behind
+    /// `Origin::Address`, the current test caller the one behind `Origin::Caller`.
+    pub fn register(contract: AccountId) -> Self {
+        ink::env::test::set_callee::<DefaultEnvironment>(contract);
+        let mock = Self {
+            state: Rc::new(RefCell::new(State {
+                contract,
+                caller: ink::env::caller::<DefaultEnvironment>(),
+                caller_origin_supported: true,
+                block: ink::env::block_number::<DefaultEnvironment>(),
+                max_scheduled_per_block: MAX_SCHEDULED_PER_BLOCK,
+                agenda: Default::default(),
+            })),
+        };
+        for func_id in 0x30001..=0x30002 {
+            ink::env::test::register_chain_extension(Method {
+                func_id,
+                state: mock.state.clone(),
+            });
+        }
+        mock
+    }
+
+    /// Sets the caller of the contract, both in the test environment and as
+    /// the account behind `Origin::Caller`.
+    pub fn set_caller(&self, caller: AccountId) {
+        ink::env::test::set_caller::<DefaultEnvironment>(caller);
+        self.state.borrow_mut().caller = caller;
+    }
+
+    /// Makes `Origin::Caller` fail with `OriginCannotBeCaller`, like runtimes
+    /// which only support `Origin::Address` do.
+    pub fn reject_caller_origin(&self) {
+        self.state.borrow_mut().caller_origin_supported = false;
+    }
+
+    pub fn set_max_scheduled_per_block(&self, max: usize) {
+        self.state.borrow_mut().max_scheduled_per_block = max;
+    }
+
+    pub fn current_block(&self) -> BlockNumber {
+        self.state.borrow().block
+    }
+
+    /// Tasks scheduled at `when`, indexed as the pallet does. Cancelled or
+    /// executed tasks leave a `None` slot behind.
+    pub fn scheduled(&self, when: BlockNumber) -> Vec<Option<Task>> {
+        self.state
+            .borrow()
+            .agenda
+            .get(&when)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Advances the test environment to block `n` and returns the tasks that
+    /// became due on the way, by block then priority. Periodic tasks are
+    /// scheduled again for their next run.
+    pub fn run_to_block(&self, n: BlockNumber) -> Vec<(BlockNumber, Task)> {
+        let mut due = Vec::new();
+        let mut state = self.state.borrow_mut();
+        while state.block < n {
+            ink::env::test::advance_block::<DefaultEnvironment>();
+            state.block += 1;
+            let block = state.block;
+            let mut tasks: Vec<Task> = state
+                .agenda
+                .remove(&block)
+                .unwrap_or_default()
+                .into_iter()
+                .flatten()
+                .collect();
+            // lower values run first, ties keep the agenda order
+            tasks.sort_by_key(|task| task.priority);
+            for task in tasks {
+                if let Some((period, count)) = task.maybe_periodic {
+                    let next = Task {
+                        maybe_periodic: (count > 1).then_some((period, count - 1)),
+                        ..task.clone()
+                    };
+                    // a full agenda drops the next run, like the pallet does
+                    let _ = state.place(block + period, next);
+                }
+                due.push((block, task));
+            }
+        }
+        due
+    }
+}
+
+/// Assets and scheduler mocks of the same contract.
+#[cfg(feature = "assets")]
+#[derive(Clone)]
+pub struct Mocks {
+    pub assets: assets_extension::mock::AssetsMock,
+    pub scheduler: SchedulerMock,
+}
+
+#[cfg(feature = "assets")]
+impl Mocks {
+    /// Registers both mocks, `contract` being the callee of the test and the
+    /// account behind `Origin::Address`.
+    pub fn register(contract: AccountId) -> Self {
+        Self {
+            assets: assets_extension::mock::AssetsMock::register(contract),
+            scheduler: SchedulerMock::register(contract),
+        }
+    }
+
+    /// Sets the caller of the contract in the test environment and in both mocks
+    pub fn set_caller(&self, caller: AccountId) {
+        self.assets.set_caller(caller);
+        self.scheduler.set_caller(caller);
+    }
+}
+
+struct Method {
+    func_id: u32,
+    state: Rc<RefCell<State>>,
+}
+
+impl Method {
+    fn status(output: &mut Vec<u8>, result: Result<(), SchedulerError>) -> u32 {
+        match result {
+            Ok(()) => {
+                result.encode_to(output);
+                0
+            }
+            Err(e) => e as u32,
+        }
+    }
+}
+
+impl ChainExtension for Method {
+    fn func_id(&self) -> u32 {
+        self.func_id
+    }
+
+    fn call(&mut self, input: &[u8], output: &mut Vec<u8>) -> u32 {
+        let input = Vec::<u8>::decode(&mut &input[..]).expect("input is a byte vector");
+        let mut input = &input[..];
+        let mut state = self.state.borrow_mut();
+        match self.func_id {
+            0x30001 => {
+                let (origin, when, maybe_periodic, priority, call) =
+                    Decode::decode(&mut input).unwrap();
+                let result = state.schedule(origin, when, maybe_periodic, priority, call);
+                Self::status(output, result)
+            }
+            0x30002 => {
+                let (origin, when, index) = Decode::decode(&mut input).unwrap();
+                Self::status(output, state.cancel(origin, when, index))
+            }
+            _ => unreachable!("unknown pallet-scheduler function"),
+        }
+    }
+}
@@ -146,15 +146,15 @@ pub mod contract {
             Ok(())
         }
 
-        // Will fail
+        /// Creates an asset owned and administered by the caller instead of the contract
         #[ink(message, payable)]
         pub fn create_caller(
             &mut self,
             asset_id: u128,
             min_balance: Balance,
         ) -> Result<(), AssetsError> {
-            let contract = self.env().caller();
-            AssetsExtension::create(Origin::Caller, asset_id, contract, min_balance)
+            let caller = self.env().caller();
+            AssetsExtension::create(Origin::Caller, asset_id, caller, min_balance)
         }
     }
 }
@@ -87,6 +87,22 @@ pub mod staking_example {
             DappsStaking::claim_staker(contract)
         }
 
+        #[ink(message)]
+        pub fn claim_staker_all(&mut self, max_eras: u32) -> Result<u32, DSError> {
+            let contract = self.env().account_id();
+            DappsStaking::claim_staker_all(contract, max_eras)
+        }
+
+        #[ink(message)]
+        pub fn claim_dapp_range(
+            &mut self,
+            account_id: AccountId,
+            from: u32,
+            to: u32,
+        ) -> Result<u32, DSError> {
+            DappsStaking::claim_dapp_range(account_id, from, to)
+        }
+
         #[ink(message)]
         pub fn set_reward_destination(&mut self, destination: u8) -> Result<(), DSError> {
             DappsStaking::set_reward_destination(destination)
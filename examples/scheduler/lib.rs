@@ -33,10 +33,10 @@ pub mod contract {
             let call_input = ContractCallInput {
                 dest: self.env().account_id(),
                 data,
-                gas_limit: (649901026000u64, 629760u64),
+                gas_limit: CALL_GAS_LIMIT,
                 storage_deposit_limit: None,
                 value: 0,
-                max_weight: 1_000_000_000_000u64,
+                max_weight: CALL_MAX_WEIGHT,
             };
             SchedulerExtension::schedule(Origin::Address, when, maybe_periodic, 0, call_input)
         }